@@ -40,10 +40,6 @@ fn is_alphabetic_lut(c: u8) -> bool {
     (LOOKUP_TABLE[c as usize] & 1) != 0
 }
 
-fn is_digit_lut(c: u8) -> bool {
-    (LOOKUP_TABLE[c as usize] & 2) != 0
-}
-
 fn is_alphanumeric_lut(c: u8) -> bool {
     (LOOKUP_TABLE[c as usize] & 3) != 0
 }
@@ -56,14 +52,6 @@ fn alphabetic_lut(i: &[u8]) -> IResult<&[u8], &[u8]> {
     take_while1(is_alphabetic_lut)(i)
 }
 
-fn digit(i: &[u8]) -> IResult<&[u8], &[u8]> {
-    take_while1(is_digit)(i)
-}
-
-fn digit_lut(i: &[u8]) -> IResult<&[u8], &[u8]> {
-    take_while1(is_digit_lut)(i)
-}
-
 fn alphanumeric(i: &[u8]) -> IResult<&[u8], &[u8]> {
     take_while1(is_alphanumeric)(i)
 }
@@ -75,11 +63,10 @@ fn alphanumeric_lut(i: &[u8]) -> IResult<&[u8], &[u8]> {
 fn alphabetic_1024_nom(bench: &mut Bencher) {
     let mut rng = rand::thread_rng();
     let mut v: Vec<u8> = std::iter::repeat(())
-        .map(|_| rng.sample(Uniform::new('A' as u8, '[' as u8)))
+        .map(|_| rng.sample(Uniform::new(b'A', b'[')))
         .take(1023)
         .collect();
     v.push(b';');
-    let v: Vec<_> = v.into();
 
     let res: IResult<_, _> = alphabetic(&v[..]);
     assert_eq!(res, Ok((&b";"[..], &v[..1023])));
@@ -91,11 +78,10 @@ fn alphabetic_1024_nom(bench: &mut Bencher) {
 fn alphabetic_1024_lut(bench: &mut Bencher) {
     let mut rng = rand::thread_rng();
     let mut v: Vec<u8> = std::iter::repeat(())
-        .map(|_| rng.sample(Uniform::new('A' as u8, '[' as u8)))
+        .map(|_| rng.sample(Uniform::new(b'A', b'[')))
         .take(1023)
         .collect();
     v.push(b';');
-    let v: Vec<_> = v.into();
 
     let res: IResult<_, _> = alphabetic_lut(&v[..]);
     assert_eq!(res, Ok((&b";"[..], &v[..1023])));
@@ -107,11 +93,10 @@ fn alphabetic_1024_lut(bench: &mut Bencher) {
 fn alphabetic_16384_nom(bench: &mut Bencher) {
     let mut rng = rand::thread_rng();
     let mut v: Vec<u8> = std::iter::repeat(())
-        .map(|_| rng.sample(Uniform::new('A' as u8, '[' as u8)))
+        .map(|_| rng.sample(Uniform::new(b'A', b'[')))
         .take(16383)
         .collect();
     v.push(b';');
-    let v: Vec<_> = v.into();
 
     let res: IResult<_, _> = alphabetic(&v[..]);
     assert_eq!(res, Ok((&b";"[..], &v[..16383])));
@@ -123,11 +108,10 @@ fn alphabetic_16384_nom(bench: &mut Bencher) {
 fn alphabetic_16384_lut(bench: &mut Bencher) {
     let mut rng = rand::thread_rng();
     let mut v: Vec<u8> = std::iter::repeat(())
-        .map(|_| rng.sample(Uniform::new('A' as u8, '[' as u8)))
+        .map(|_| rng.sample(Uniform::new(b'A', b'[')))
         .take(16383)
         .collect();
     v.push(b';');
-    let v: Vec<_> = v.into();
 
     let res: IResult<_, _> = alphabetic_lut(&v[..]);
     assert_eq!(res, Ok((&b";"[..], &v[..16383])));
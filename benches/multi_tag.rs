@@ -1,3 +1,4 @@
+#![allow(non_snake_case)]
 #[macro_use]
 extern crate bencher;
 #[macro_use]
@@ -6,12 +7,19 @@ extern crate nom;
 extern crate nom_specialized;
 
 use bencher::Bencher;
-use nom::{IResult, Parser, Err, Needed, HexDisplay};
+use nom::{IResult, Parser, Err, Needed};
 use nom::error::{Error, ErrorKind, ParseError};
 use nom::branch::alt;
 //use nom::bytes::streaming::tag;
-use nom_specialized::combinators::tag_unrolled as tag;
-use nom_specialized::avx::*;
+use nom_specialized::combinators::streaming::tag_unrolled;
+use nom_specialized::avx::MultiTag;
+use nom_specialized::static_multitag;
+
+// fixes the error type, `Parser::map` cannot infer it
+fn tag<'a>(t: &'static [u8]) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    let parser = tag_unrolled(t);
+    move |i| parser.parse(i)
+}
 
 fn nom_parser(i: &[u8]) -> IResult<&[u8], u8> {
     alt((
@@ -82,12 +90,12 @@ fn naive(i: &[u8]) -> IResult<&[u8], u8> {
 }
 
 fn manual(i: &[u8]) -> IResult<&[u8], u8> {
-    match i.get(0) {
+    match i.first() {
         Some(b'A') => {
             match i.get(1) {
                 Some(b'c') => {
                     // FIXME: not handling the Incomplete case
-                    if (&i[2..]).starts_with(&b"cept"[..]) {
+                    if i[2..].starts_with(&b"cept"[..]) {
                         match i.get(7) {
                             Some(b'-') => {
                                 alt((
@@ -109,7 +117,7 @@ fn manual(i: &[u8]) -> IResult<&[u8], u8> {
             }
         },
         Some(b'C') => {
-            if (&i[1..]).starts_with(&b"ontent-"[..]) {
+            if i[1..].starts_with(&b"ontent-"[..]) {
                 alt((
                         tag(&b"Encoding"[..]).map(|_| 4u8),
                         tag(&b"Length"[..]).map(|_| 5u8),
@@ -136,7 +144,7 @@ fn manual(i: &[u8]) -> IResult<&[u8], u8> {
 }
 
 lazy_static! {
-        static ref RE: regex::bytes::RegexSet = regex::bytes::RegexSet::new(&[
+        static ref RE: regex::bytes::RegexSet = regex::bytes::RegexSet::new([
                r"Accept-Charset",
                r"Accept-Encoding",
                r"Accept",
@@ -163,19 +171,19 @@ fn re(i: &[u8]) -> IResult<&[u8], u8> {
     }
 }
 
-fn avx_parser<'a>() -> impl Fn(&'a[u8]) -> IResult<&'a[u8], u8> {
-    let parser1 = multitag::<Error<&'a[u8]>>(&[
-        &b"Acce"[..], &b"Auth"[..], &b"Cont"[..], &b"Date"[..],
-        &b"Expe"[..], &b"Forw"[..], &b"Host"[..], &b"If-M"[..]
-    ]);
-    let parser2 = multitag::<Error<&'a[u8]>>(&[
-        &b"Refe"[..], &b"User"[..], &b"Upgr"[..], &b"Via:"[..],
-        &b"X-Forwarded-For"[..],
-    ]);
+static_multitag! {
+    static PREFIXES: MultiTag = [
+        b"Acce", b"Auth", b"Cont", b"Date",
+        b"Expe", b"Forw", b"Host", b"If-M",
+        b"Refe", b"User", b"Upgr", b"Via:",
+        b"X-Forwarded-For",
+    ];
+}
 
+fn avx_parser<'a>() -> impl Fn(&'a[u8]) -> IResult<&'a[u8], u8> {
     move |i: &[u8]| {
-        if let Ok((i, idx)) = parser1(i) {
-            match idx {
+        match PREFIXES.parse::<Error<&[u8]>>(i) {
+            Ok((i, (idx, _))) => match idx {
                 0 => alt((
                       tag(&b"pt-Charset"[..]).map(|_| 0u8),
                       tag(&b"pt-Encoding"[..]).map(|_| 1u8),
@@ -186,25 +194,19 @@ fn avx_parser<'a>() -> impl Fn(&'a[u8]) -> IResult<&'a[u8], u8> {
                       tag(&b"ent-Encoding"[..]).map(|_| 4u8),
                       tag(&b"ent-Length"[..]).map(|_| 5u8),
                     ))(i),
-                4 => Ok((i, 6u8)),
-                5 => tag(&b"ct"[..]).map(|_| 7u8).parse(i),
-                6 => tag(&b"arded"[..]).map(|_| 8u8).parse(i),
-                7 => Ok((i, 9u8)),
-                8 => tag(&b"odified-Since"[..]).map(|_| 10u8).parse(i),
-                _ => Err(Err::Error(Error::from_error_kind(i, ErrorKind::Tag)))
-            }
-        } else if let Ok((i, idx)) = parser2(i) {
-            //println!("got idx: {}, i: {}", idx, std::str::from_utf8(i).unwrap());
-            match idx {
-                0 => tag(&b"rer"[..]).map(|_| 11u8).parse(i),
-                1 => tag(&b"-Agent"[..]).map(|_| 12u8).parse(i),
-                2 => tag(&b"ade"[..]).map(|_| 13u8).parse(i),
-                3 => Ok((i, 14u8)),
-                4 => Ok((i, 15u8)),
+                3 => Ok((i, 6u8)),
+                4 => tag(&b"ct"[..]).map(|_| 7u8).parse(i),
+                5 => tag(&b"arded"[..]).map(|_| 8u8).parse(i),
+                6 => Ok((i, 9u8)),
+                7 => tag(&b"odified-Since"[..]).map(|_| 10u8).parse(i),
+                8 => tag(&b"rer"[..]).map(|_| 11u8).parse(i),
+                9 => tag(&b"-Agent"[..]).map(|_| 12u8).parse(i),
+                10 => tag(&b"ade"[..]).map(|_| 13u8).parse(i),
+                11 => Ok((i, 14u8)),
+                12 => Ok((i, 15u8)),
                 _ => Err(Err::Error(Error::from_error_kind(i, ErrorKind::Tag))),
-            }
-        } else {
-            Err(Err::Error(Error::from_error_kind(i, ErrorKind::Tag)))
+            },
+            Err(_) => Err(Err::Error(Error::from_error_kind(i, ErrorKind::Tag))),
         }
     }
 }
@@ -252,7 +254,6 @@ fn multitag_Accept_re(bench: &mut Bencher) {
 fn multitag_Accept_avx(bench: &mut Bencher) {
     let parser = avx_parser();
 
-    // the parser needs 16 bytes
     let input = b"Accept: ABCDER\r\n";
 
     let res: IResult<_, _> = parser(&input[..]);
@@ -4,8 +4,8 @@ extern crate nom;
 extern crate nom_specialized;
 
 use bencher::Bencher;
-use nom::IResult;
 use nom::error::Error;
+use nom::IResult;
 
 fn tag_4_nom(bench: &mut Bencher) {
     let input = b"ABCDABCDABCDABCDabcd";
@@ -21,23 +21,23 @@ fn tag_4_nom(bench: &mut Bencher) {
 fn tag_4_sse2(bench: &mut Bencher) {
     let input = b"ABCDABCDABCDABCDabcd";
 
-    let parser = nom_specialized::combinators::tag_sse2(&b"ABCD"[..]);
-    let res: IResult<_, _> = parser(&input[..]);
+    let parser = nom_specialized::combinators::streaming::tag_sse2(&b"ABCD"[..]);
+    let res: IResult<_, _> = parser.parse(&input[..]);
     assert_eq!(res, Ok((&b"ABCDABCDABCDabcd"[..], &b"ABCD"[..])));
 
     bench.bytes = 4;
-    bench.iter(|| parser(&input[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&input[..]))
 }
 
 fn tag_4_unrolled(bench: &mut Bencher) {
     let input = b"ABCDABCDABCDABCDabcd";
 
-    let parser = nom_specialized::combinators::tag_unrolled(&b"ABCD"[..]);
-    let res: IResult<_, _> = parser(&input[..]);
+    let parser = nom_specialized::combinators::streaming::tag_unrolled(&b"ABCD"[..]);
+    let res: IResult<_, _> = parser.parse(&input[..]);
     assert_eq!(res, Ok((&b"ABCDABCDABCDabcd"[..], &b"ABCD"[..])));
 
     bench.bytes = 4;
-    bench.iter(|| parser(&input[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&input[..]))
 }
 
 fn tag_16_nom(bench: &mut Bencher) {
@@ -54,23 +54,23 @@ fn tag_16_nom(bench: &mut Bencher) {
 fn tag_16_sse2(bench: &mut Bencher) {
     let input = b"ABCDABCDABCDABCDabcd";
 
-    let parser = nom_specialized::combinators::tag_sse2(&b"ABCDABCDABCDABCD"[..]);
-    let res: IResult<_, _> = parser(&input[..]);
+    let parser = nom_specialized::combinators::streaming::tag_sse2(&b"ABCDABCDABCDABCD"[..]);
+    let res: IResult<_, _> = parser.parse(&input[..]);
     assert_eq!(res, Ok((&b"abcd"[..], &b"ABCDABCDABCDABCD"[..])));
 
     bench.bytes = 16;
-    bench.iter(|| parser(&input[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&input[..]))
 }
 
 fn tag_16_unrolled(bench: &mut Bencher) {
     let input = b"ABCDABCDABCDABCDabcd";
 
-    let parser = nom_specialized::combinators::tag_unrolled(&b"ABCDABCDABCDABCD"[..]);
-    let res: IResult<_, _> = parser(&input[..]);
+    let parser = nom_specialized::combinators::streaming::tag_unrolled(&b"ABCDABCDABCDABCD"[..]);
+    let res: IResult<_, _> = parser.parse(&input[..]);
     assert_eq!(res, Ok((&b"abcd"[..], &b"ABCDABCDABCDABCD"[..])));
 
     bench.bytes = 16;
-    bench.iter(|| parser(&input[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&input[..]))
 }
 
 fn tag_32_nom(bench: &mut Bencher) {
@@ -87,23 +87,23 @@ fn tag_32_nom(bench: &mut Bencher) {
 fn tag_32_sse2(bench: &mut Bencher) {
     let input = b"ABCDABCDABCDABCDABCDABCDABCDABCDabcd";
 
-    let parser = nom_specialized::combinators::tag_sse2(&b"ABCDABCDABCDABCDABCDABCDABCDABCD"[..]);
-    let res: IResult<_, _> = parser(&input[..]);
+    let parser = nom_specialized::combinators::streaming::tag_sse2(&b"ABCDABCDABCDABCDABCDABCDABCDABCD"[..]);
+    let res: IResult<_, _> = parser.parse(&input[..]);
     assert_eq!(res, Ok((&b"abcd"[..], &b"ABCDABCDABCDABCDABCDABCDABCDABCD"[..])));
 
     bench.bytes = 32;
-    bench.iter(|| parser(&input[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&input[..]))
 }
 
 fn tag_32_unrolled(bench: &mut Bencher) {
     let input = b"ABCDABCDABCDABCDABCDABCDABCDABCDabcd";
 
-    let parser = nom_specialized::combinators::tag_unrolled(&b"ABCDABCDABCDABCDABCDABCDABCDABCD"[..]);
-    let res: IResult<_, _> = parser(&input[..]);
+    let parser = nom_specialized::combinators::streaming::tag_unrolled(&b"ABCDABCDABCDABCDABCDABCDABCDABCD"[..]);
+    let res: IResult<_, _> = parser.parse(&input[..]);
     assert_eq!(res, Ok((&b"abcd"[..], &b"ABCDABCDABCDABCDABCDABCDABCDABCD"[..])));
 
     bench.bytes = 32;
-    bench.iter(|| parser(&input[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&input[..]))
 }
 
 benchmark_group!(
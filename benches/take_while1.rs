@@ -7,8 +7,12 @@ use bencher::Bencher;
 use nom::IResult;
 use nom::error::Error;
 
+const fn is_a(c: u8) -> bool {
+    c == b'a'
+}
+
 fn take_while1_1024_nom(bench: &mut Bencher) {
-    let mut v = std::iter::repeat(b'a').take(1023).collect::<Vec<_>>();
+    let mut v = vec![b'a'; 1023];
     v.push(b'b');
 
     let parser = nom::bytes::streaming::take_while1(|c| c == b'a');
@@ -20,33 +24,61 @@ fn take_while1_1024_nom(bench: &mut Bencher) {
 }
 
 fn take_while1_1024_unrolled(bench: &mut Bencher) {
-    let mut v = std::iter::repeat(b'a').take(1023).collect::<Vec<_>>();
+    let mut v = vec![b'a'; 1023];
     v.push(b'b');
 
-    let parser = nom_specialized::combinators::take_while1_unrolled(|c| c == b'a');
-    let res: IResult<_, _> = parser(&v[..]);
+    let parser = nom_specialized::combinators::streaming::take_while1_unrolled(|c| c == b'a');
+    let res: IResult<_, _> = parser.parse(&v[..]);
     assert_eq!(res, Ok((&b"b"[..], &v[..1023])));
 
     bench.bytes = 1024;
-    bench.iter(|| parser(&v[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
 }
 
 fn take_while1_1024_sse2(bench: &mut Bencher) {
-    let mut v = std::iter::repeat(b'a').take(1023).collect::<Vec<_>>();
+    let mut v = vec![b'a'; 1023];
     v.push(b'b');
 
-    let ranges = b"\0`b\xFF";
+    let ranges = nom_specialized::make_ranges!(is_a);
 
-    let parser = nom_specialized::combinators::take_while1_sse2::<_, Error<&[u8]>>(|c| c == b'a', &ranges[..]);
-    let res = parser(&v[..]);
+    let parser = nom_specialized::combinators::streaming::take_while1_sse2(ranges);
+    let res: IResult<_, _> = parser.parse(&v[..]);
     assert_eq!(res, Ok((&b"b"[..], &v[..1023])));
 
     bench.bytes = 1024;
-    bench.iter(|| parser(&v[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
+}
+
+fn take_while1_1024_avx2(bench: &mut Bencher) {
+    let mut v = vec![b'a'; 1023];
+    v.push(b'b');
+
+    let ranges = nom_specialized::make_ranges!(is_a);
+
+    let parser = nom_specialized::combinators::streaming::take_while1_avx2(ranges);
+    let res: IResult<_, _> = parser.parse(&v[..]);
+    assert_eq!(res, Ok((&b"b"[..], &v[..1023])));
+
+    bench.bytes = 1024;
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
+}
+
+fn take_while1_1024_shufti(bench: &mut Bencher) {
+    let mut v = vec![b'a'; 1023];
+    v.push(b'b');
+
+    let set = nom_specialized::make_shufti!(is_a);
+
+    let parser = nom_specialized::combinators::streaming::take_while1_shufti(&set);
+    let res: IResult<_, _> = parser.parse(&v[..]);
+    assert_eq!(res, Ok((&b"b"[..], &v[..1023])));
+
+    bench.bytes = 1024;
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
 }
 
 fn take_while1_50_nom(bench: &mut Bencher) {
-    let mut v = std::iter::repeat(b'a').take(49).collect::<Vec<_>>();
+    let mut v = vec![b'a'; 49];
     v.push(b'b');
 
     let parser = nom::bytes::streaming::take_while1(|c| c == b'a');
@@ -58,33 +90,61 @@ fn take_while1_50_nom(bench: &mut Bencher) {
 }
 
 fn take_while1_50_unrolled(bench: &mut Bencher) {
-    let mut v = std::iter::repeat(b'a').take(49).collect::<Vec<_>>();
+    let mut v = vec![b'a'; 49];
     v.push(b'b');
 
-    let parser = nom_specialized::combinators::take_while1_unrolled(|c| c == b'a');
-    let res: IResult<_, _> = parser(&v[..]);
+    let parser = nom_specialized::combinators::streaming::take_while1_unrolled(|c| c == b'a');
+    let res: IResult<_, _> = parser.parse(&v[..]);
     assert_eq!(res, Ok((&b"b"[..], &v[..49])));
 
     bench.bytes = 50;
-    bench.iter(|| parser(&v[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
 }
 
 fn take_while1_50_sse2(bench: &mut Bencher) {
-    let mut v = std::iter::repeat(b'a').take(49).collect::<Vec<_>>();
+    let mut v = vec![b'a'; 49];
     v.push(b'b');
 
-    let ranges = b"\0`b\xFF";
+    let ranges = nom_specialized::make_ranges!(is_a);
 
-    let parser = nom_specialized::combinators::take_while1_sse2::<_, Error<&[u8]>>(|c| c == b'a', &ranges[..]);
-    let res = parser(&v[..]);
+    let parser = nom_specialized::combinators::streaming::take_while1_sse2(ranges);
+    let res: IResult<_, _> = parser.parse(&v[..]);
     assert_eq!(res, Ok((&b"b"[..], &v[..49])));
 
     bench.bytes = 50;
-    bench.iter(|| parser(&v[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
+}
+
+fn take_while1_50_avx2(bench: &mut Bencher) {
+    let mut v = vec![b'a'; 49];
+    v.push(b'b');
+
+    let ranges = nom_specialized::make_ranges!(is_a);
+
+    let parser = nom_specialized::combinators::streaming::take_while1_avx2(ranges);
+    let res: IResult<_, _> = parser.parse(&v[..]);
+    assert_eq!(res, Ok((&b"b"[..], &v[..49])));
+
+    bench.bytes = 50;
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
+}
+
+fn take_while1_50_shufti(bench: &mut Bencher) {
+    let mut v = vec![b'a'; 49];
+    v.push(b'b');
+
+    let set = nom_specialized::make_shufti!(is_a);
+
+    let parser = nom_specialized::combinators::streaming::take_while1_shufti(&set);
+    let res: IResult<_, _> = parser.parse(&v[..]);
+    assert_eq!(res, Ok((&b"b"[..], &v[..49])));
+
+    bench.bytes = 50;
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
 }
 
 fn take_while1_16384_nom(bench: &mut Bencher) {
-    let mut v = std::iter::repeat(b'a').take(16383).collect::<Vec<_>>();
+    let mut v = vec![b'a'; 16383];
     v.push(b'b');
 
     let parser = nom::bytes::streaming::take_while1(|c| c == b'a');
@@ -96,29 +156,57 @@ fn take_while1_16384_nom(bench: &mut Bencher) {
 }
 
 fn take_while1_16384_unrolled(bench: &mut Bencher) {
-    let mut v = std::iter::repeat(b'a').take(16383).collect::<Vec<_>>();
+    let mut v = vec![b'a'; 16383];
     v.push(b'b');
 
-    let parser = nom_specialized::combinators::take_while1_unrolled(|c| c == b'a');
-    let res: IResult<_, _> = parser(&v[..]);
+    let parser = nom_specialized::combinators::streaming::take_while1_unrolled(|c| c == b'a');
+    let res: IResult<_, _> = parser.parse(&v[..]);
     assert_eq!(res, Ok((&b"b"[..], &v[..16383])));
 
     bench.bytes = 16384;
-    bench.iter(|| parser(&v[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
 }
 
 fn take_while1_16384_sse2(bench: &mut Bencher) {
-    let mut v = std::iter::repeat(b'a').take(16383).collect::<Vec<_>>();
+    let mut v = vec![b'a'; 16383];
     v.push(b'b');
 
-    let ranges = b"\0`b\xFF";
+    let ranges = nom_specialized::make_ranges!(is_a);
 
-    let parser = nom_specialized::combinators::take_while1_sse2::<_, Error<&[u8]>>(|c| c == b'a', &ranges[..]);
-    let res = parser(&v[..]);
+    let parser = nom_specialized::combinators::streaming::take_while1_sse2(ranges);
+    let res: IResult<_, _> = parser.parse(&v[..]);
     assert_eq!(res, Ok((&b"b"[..], &v[..16383])));
 
     bench.bytes = 16384;
-    bench.iter(|| parser(&v[..]))
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
+}
+
+fn take_while1_16384_avx2(bench: &mut Bencher) {
+    let mut v = vec![b'a'; 16383];
+    v.push(b'b');
+
+    let ranges = nom_specialized::make_ranges!(is_a);
+
+    let parser = nom_specialized::combinators::streaming::take_while1_avx2(ranges);
+    let res: IResult<_, _> = parser.parse(&v[..]);
+    assert_eq!(res, Ok((&b"b"[..], &v[..16383])));
+
+    bench.bytes = 16384;
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
+}
+
+fn take_while1_16384_shufti(bench: &mut Bencher) {
+    let mut v = vec![b'a'; 16383];
+    v.push(b'b');
+
+    let set = nom_specialized::make_shufti!(is_a);
+
+    let parser = nom_specialized::combinators::streaming::take_while1_shufti(&set);
+    let res: IResult<_, _> = parser.parse(&v[..]);
+    assert_eq!(res, Ok((&b"b"[..], &v[..16383])));
+
+    bench.bytes = 16384;
+    bench.iter(|| parser.parse::<Error<&[u8]>>(&v[..]))
 }
 
 benchmark_group!(
@@ -126,11 +214,17 @@ benchmark_group!(
     take_while1_50_nom,
     take_while1_50_unrolled,
     take_while1_50_sse2,
+    take_while1_50_avx2,
+    take_while1_50_shufti,
     take_while1_1024_nom,
     take_while1_1024_unrolled,
     take_while1_1024_sse2,
+    take_while1_1024_avx2,
+    take_while1_1024_shufti,
     take_while1_16384_nom,
     take_while1_16384_unrolled,
     take_while1_16384_sse2,
+    take_while1_16384_avx2,
+    take_while1_16384_shufti,
 );
 benchmark_main!(benches);
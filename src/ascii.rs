@@ -0,0 +1,95 @@
+//! ASCII case folding
+//!
+//! `tag_no_case` and the case insensitive multitag compare lowercased
+//! bytes, one at a time, 8 at a time in a `u64`, or in SIMD vectors. They
+//! all use these functions, so they agree on which bytes are letters.
+/* Copyright (C) 2020 Geoffroy Couprie */
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+#[inline(always)]
+pub(crate) const fn to_ascii_lowercase_scalar(c: u8) -> u8 {
+    if c.is_ascii_uppercase() {
+        c | 0x20
+    } else {
+        c
+    }
+}
+
+/// lowercases the ASCII letters in the 8 bytes of `word`
+#[inline(always)]
+pub(crate) fn to_ascii_lowercase_swar(word: u64) -> u64 {
+    const LOW_BITS: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+    const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+    const ONES: u64 = 0x0101_0101_0101_0101;
+
+    let heptets = word & LOW_BITS;
+    // the high bit of each byte is set if it is above 'Z'
+    let above_z = heptets + ONES * (0x7f - b'Z' as u64);
+    // the high bit of each byte is set if it is 'A' or above
+    let from_a = heptets + ONES * (0x80 - b'A' as u64);
+    let is_upper = !word & (from_a ^ above_z) & HIGH_BITS;
+
+    word | (is_upper >> 2)
+}
+
+/// lowercases the ASCII letters of `v`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+#[inline]
+pub(crate) unsafe fn to_ascii_lowercase_sse2(v: __m128i) -> __m128i {
+    // bytes above 0x7F are negative, so they are not between 'A' and 'Z'
+    let before_a = _mm_set1_epi8(b'A' as i8 - 1);
+    let after_z = _mm_set1_epi8(b'Z' as i8 + 1);
+    let is_upper = _mm_and_si128(_mm_cmpgt_epi8(v, before_a), _mm_cmpgt_epi8(after_z, v));
+    _mm_or_si128(v, _mm_and_si128(is_upper, _mm_set1_epi8(0x20)))
+}
+
+/// lowercases the ASCII letters of `v`, see `to_ascii_lowercase_sse2`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline]
+pub(crate) unsafe fn to_ascii_lowercase_avx2(v: __m256i) -> __m256i {
+    let before_a = _mm256_set1_epi8(b'A' as i8 - 1);
+    let after_z = _mm256_set1_epi8(b'Z' as i8 + 1);
+    let is_upper = _mm256_and_si256(_mm256_cmpgt_epi8(v, before_a), _mm256_cmpgt_epi8(after_z, v));
+    _mm256_or_si256(v, _mm256_and_si256(is_upper, _mm256_set1_epi8(0x20)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowercase() {
+        let bytes: Vec<u8> = (0..=255u8).collect();
+        let expected: Vec<u8> = bytes.iter().map(|c| c.to_ascii_lowercase()).collect();
+
+        let scalar: Vec<u8> = bytes.iter().map(|&c| to_ascii_lowercase_scalar(c)).collect();
+        assert_eq!(scalar, expected);
+
+        for (chunk, expected) in bytes.chunks(8).zip(expected.chunks(8)) {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(chunk);
+            assert_eq!(&to_ascii_lowercase_swar(u64::from_le_bytes(word)).to_le_bytes()[..], expected);
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        for (chunk, expected) in bytes.chunks(32).zip(expected.chunks(32)) {
+            let mut lowered = [0u8; 32];
+            unsafe {
+                let low = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+                _mm_storeu_si128(lowered.as_mut_ptr() as *mut __m128i, to_ascii_lowercase_sse2(low));
+            }
+            assert_eq!(&lowered[..16], &expected[..16]);
+
+            if crate::cpu::has_avx2() {
+                unsafe {
+                    let v = _mm256_loadu_si256(chunk.as_ptr() as *const __m256i);
+                    _mm256_storeu_si256(lowered.as_mut_ptr() as *mut __m256i, to_ascii_lowercase_avx2(v));
+                }
+                assert_eq!(&lowered[..], expected);
+            }
+        }
+    }
+}
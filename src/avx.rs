@@ -1,294 +1,2341 @@
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
-use nom::{IResult, HexDisplay, Needed, Err, error::{ParseError, ErrorKind}};
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use nom::{IResult, Needed, Err, Parser, error::{ParseError, ErrorKind}};
+use crate::ascii::to_ascii_lowercase_scalar;
+#[cfg(target_arch = "x86_64")]
+use crate::ascii::{to_ascii_lowercase_avx2, to_ascii_lowercase_sse2};
+use crate::mode::{Mode, Streaming};
+use crate::padded::PaddedInput;
 
-pub fn multitag<'a, Error: ParseError<&'a [u8]>>(tags:&[&[u8]])
-  -> impl Fn(&'a [u8]) -> IResult<&'a [u8], usize, Error>{
+/// which tag [`MultiTag`] returns when several tags match the input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchPolicy {
+  /// the first tag in declaration order, like `alt((tag(..), tag(..)))`
+  FirstDeclared,
+  /// the longest tag, the first declared one if several have the same length
+  Longest,
+}
 
-  let Masks { cmp, shuf_mask, high_mask, low_mask, ids } = prepare(tags);
+/// parser built by [`multitag`] or [`MultiTagBuilder`]
+///
+/// the tags are packed in groups of at most 32 bytes, see [`prepare_groups`]
+#[derive(Clone)]
+pub struct MultiTag<M = Streaming> {
+  groups: Cow<'static, [MasksVec]>,
+  policy: MatchPolicy,
+  delimiters: Option<Delimiters>,
+  mode: PhantomData<M>,
+}
 
-  move |i: &'a[u8]| {
-      if i.len() < 16 {
-          return Err(Err::Incomplete(Needed::Unknown));
-      }
+impl<M: Mode> MultiTag<M> {
+  /// returns the index of the matched tag, and the consumed tag
+  ///
+  /// in streaming mode, an input shorter than a tag it could still match
+  /// returns `Err::Incomplete` with the number of missing bytes, including
+  /// the delimiter if there are [delimiters](MultiTag::with_delimiters)
+  pub fn parse<'a, Error: ParseError<&'a [u8]>>(&self, i: &'a [u8]) -> IResult<&'a [u8], (usize, &'a [u8]), Error> {
+    // short inputs are copied to a zero padded buffer so we can still
+    // load 16 bytes, the padding is then ignored by `find`
+    let mut buf = [0u8; 16];
+    let data = if i.len() < 16 {
+        buf[..i.len()].copy_from_slice(i);
+        &buf[..]
+    } else {
+        i
+    };
 
-    let input = load16(i);
-    let cmp_mask = load(&cmp);
-    let shuf_mask = load(&shuf_mask[..]);
-    let shuffled = unsafe { _mm256_shuffle_epi8(input, shuf_mask) };
-    let cmpres = unsafe { _mm256_cmpeq_epi8(shuffled, cmp_mask) };
-    let maskres = unsafe { _mm256_movemask_epi8(cmpres) };
-    let tmp_mask = maskres as u32 & !high_mask;
-    let (tmp_mask2, _) = tmp_mask.overflowing_add(low_mask);
-    let tmp_mask3 = tmp_mask2 & maskres as u32;
-    let res = tmp_mask3 & high_mask;
+    match self.find(data, i.len()) {
+        Lookup::Match { idx, len } => Ok((&i[len..], (idx, &i[..len]))),
+        Lookup::Incomplete(needed) => Err(Err::Incomplete(Needed::new(needed))),
+        Lookup::NoMatch => Err(Err::Error(Error::from_error_kind(i, ErrorKind::Tag))),
+    }
+  }
+
+  /// same as `parse`, short inputs are loaded directly from the padding
+  pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(&self, i: PaddedInput<'a>) -> IResult<PaddedInput<'a>, (usize, &'a [u8]), Error> {
+    match self.find(i.with_padding(), i.len()) {
+        Lookup::Match { idx, len } => {
+            let (rest, tag) = i.take_split(len);
+            Ok((rest, (idx, tag)))
+        }
+        Lookup::Incomplete(needed) => Err(Err::Incomplete(Needed::new(needed))),
+        Lookup::NoMatch => Err(Err::Error(Error::from_error_kind(i, ErrorKind::Tag))),
+    }
+  }
+
+  /// parser using groups built at compile time, usually a `static` built by
+  /// [`prepare_groups`] with [`group_count`] groups
+  ///
+  /// the policy is [`MatchPolicy::FirstDeclared`], see [`MultiTag::with_policy`]
+  pub const fn from_groups(groups: &'static [MasksVec]) -> Self {
+    MultiTag { groups: Cow::Borrowed(groups), policy: MatchPolicy::FirstDeclared, delimiters: None, mode: PhantomData }
+  }
+
+  pub const fn with_policy(mut self, policy: MatchPolicy) -> Self {
+    self.policy = policy;
+    self
+  }
+
+  /// only matches the tags followed by a byte `c` with `table[c] != 0`, or
+  /// by the end of the input in complete mode, like a table built by
+  /// [make_lookup_table](crate::make_lookup_table)
+  ///
+  /// a tag followed by another byte is ignored, so a longer tag can match
+  /// instead. In streaming mode, a tag at the end of the input returns
+  /// `Err::Incomplete` until its next byte is available
+  pub const fn with_delimiters(mut self, table: &[u8; 256]) -> Self {
+    self.delimiters = Some(Delimiters::from_lookup_table(table));
+    self
+  }
 
-    let cnt = unsafe { _lzcnt_u32(res) };
+  /// keeps the ends of the matched tags followed by a delimiter, and moves
+  /// the ones at the end of the input to the partial matches in streaming
+  /// mode. Returns them, and the number of bytes needed after a partial
+  /// match
+  #[inline(always)]
+  fn delimit(&self, masks: &MasksVec, full: u32, partial: u32, data: &[u8], len: usize) -> (u32, u32, usize) {
+    let delimiters = match &self.delimiters {
+        Some(delimiters) => delimiters,
+        None => return (full, partial, 0),
+    };
 
-    if cnt < 31 {
-        let idx = ids[(31 - cnt) as usize];
-        if idx == 0xFFu8 {
-            Err(Err::Error(Error::from_error_kind(i, ErrorKind::Tag)))
+    let mut kept = 0u32;
+    let mut partial = partial;
+    let mut ends = full;
+    while ends != 0 {
+        let position = ends.trailing_zeros() as usize;
+        let tag_len = masks.lens[position] as usize;
+        if tag_len < len {
+            if delimiters.contains(data[tag_len]) {
+                kept |= 1 << position;
+            }
+        } else if M::STREAMING {
+            partial |= 1 << position;
         } else {
-            Ok((&i[4..], idx as usize))
+            kept |= 1 << position;
         }
-    } else {
-        Err(Err::Error(Error::from_error_kind(i, ErrorKind::Tag)))
+        ends &= ends - 1;
+    }
+
+    (kept, partial, 1)
+  }
+
+  /// looks for the tag matching the start of `data`, according to the policy
+  ///
+  /// the tags are packed in declaration order, so the lowest bit of the
+  /// first group with a match is the first declared tag. In streaming mode,
+  /// a tag the input is a prefix of may still match, so it has the same
+  /// priority as a matched tag
+  ///
+  /// `data` must contain at least 16 bytes, of which the first `len` are the input
+  #[inline(always)]
+  fn find(&self, data: &[u8], len: usize) -> Lookup {
+    match self.policy {
+      MatchPolicy::FirstDeclared => {
+        for masks in self.groups.iter() {
+            let (full, partial) = masks.candidates(eq_mask(masks, data), len);
+            let (full, partial, delimiter) = self.delimit(masks, full, partial, data, len);
+            let partial = if M::STREAMING { partial } else { 0 };
+
+            let res = full | partial;
+            if res != 0 {
+                let position = res.trailing_zeros() as usize;
+                let tag_len = masks.lens[position] as usize;
+                return if full & (1 << position) != 0 {
+                    Lookup::Match { idx: masks.ids[position] as usize, len: tag_len }
+                } else {
+                    Lookup::Incomplete(tag_len - len + delimiter)
+                };
+            }
+        }
+
+        Lookup::NoMatch
+      }
+      MatchPolicy::Longest => {
+        let mut found = Lookup::NoMatch;
+        let mut needed: Option<usize> = None;
+        for masks in self.groups.iter() {
+            let (full, partial) = masks.candidates(eq_mask(masks, data), len);
+            let (mut full, mut partial, delimiter) = self.delimit(masks, full, partial, data, len);
+
+            while full != 0 {
+                let position = full.trailing_zeros() as usize;
+                let tag_len = masks.lens[position] as usize;
+                let longer = match found {
+                    Lookup::Match { len: found_len, .. } => tag_len > found_len,
+                    _ => true,
+                };
+                if longer {
+                    found = Lookup::Match { idx: masks.ids[position] as usize, len: tag_len };
+                }
+                full &= full - 1;
+            }
+
+            // a partially matched tag is longer than any matched one, and
+            // like `tag`, the longest one needs all its missing bytes
+            while M::STREAMING && partial != 0 {
+                let position = partial.trailing_zeros() as usize;
+                let missing = masks.lens[position] as usize - len + delimiter;
+                needed = Some(needed.map_or(missing, |needed| needed.max(missing)));
+                partial &= partial - 1;
+            }
+        }
+
+        match needed {
+            Some(needed) => Lookup::Incomplete(needed),
+            None => found,
+        }
+      }
     }
   }
 }
 
-pub fn process(m: &MasksVec, input: __m256i) -> u8 {
-    let shuffled = unsafe { _mm256_shuffle_epi8(input, m.shuf_mask) };
-    let cmpres = unsafe { _mm256_cmpeq_epi8(shuffled, m.cmp) };
-    let maskres = unsafe { _mm256_movemask_epi8(cmpres) };
-    let tmp_mask = maskres as u32 & !m.high_mask;
-    let (tmp_mask2, _) = tmp_mask.overflowing_add(m.low_mask);
-    let tmp_mask3 = tmp_mask2 & maskres as u32;
-    let res = tmp_mask3 & m.high_mask;
+/// result of `MultiTag::find`
+enum Lookup {
+  Match { idx: usize, len: usize },
+  /// number of missing bytes
+  Incomplete(usize),
+  NoMatch,
+}
 
-    let cnt = unsafe { _lzcnt_u32(res) };
+/// bit set of the bytes accepted after a tag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Delimiters([u64; 4]);
 
-    if cnt < 31 {
-        let idx = m.ids[(31 - cnt) as usize];
-        idx
-    } else {
-        0xFFu8
+impl Delimiters {
+    const fn from_lookup_table(table: &[u8; 256]) -> Self {
+        let mut bits = [0u64; 4];
+        let mut c = 0usize;
+        loop {
+            if c == 256 {
+                break;
+            }
+            if table[c] != 0 {
+                bits[c / 64] |= 1 << (c % 64);
+            }
+            c += 1;
+        }
+
+        Delimiters(bits)
+    }
+
+    #[inline(always)]
+    fn contains(&self, c: u8) -> bool {
+        self.0[c as usize / 64] & (1 << (c % 64)) != 0
     }
 }
 
-pub struct Masks {
-  cmp: [u8; 32],
-  shuf_mask: [u8; 32],
-  high_mask: u32,
-  low_mask: u32,
-  ids: [u8; 32],
+impl<'a, M: Mode, Error: ParseError<&'a [u8]>> Parser<&'a [u8], (usize, &'a [u8]), Error> for MultiTag<M> {
+  fn parse(&mut self, i: &'a [u8]) -> IResult<&'a [u8], (usize, &'a [u8]), Error> {
+    MultiTag::parse(self, i)
+  }
 }
 
-pub const fn prepare(strings: &[&[u8]]) -> Masks {
-    let mut cmp = [0u8; 32];
-    let mut shuf_mask = [0u8; 32];
-    let mut high_mask = 0u32;
-    let mut low_mask = 0u32;
-    let mut ids = [0xFFu8; 32];
+impl<'a, M: Mode, Error: ParseError<PaddedInput<'a>>> Parser<PaddedInput<'a>, (usize, &'a [u8]), Error> for MultiTag<M> {
+  fn parse(&mut self, i: PaddedInput<'a>) -> IResult<PaddedInput<'a>, (usize, &'a [u8]), Error> {
+    MultiTag::parse_padded(self, i)
+  }
+}
+
+/// matches the tags at the start of the input, returning the index of the tag
+/// and the consumed tag
+///
+/// the tags can be up to 16 bytes long, and are packed in as many groups as
+/// needed. To build the groups at compile time, see [`MultiTag::from_groups`]
+///
+/// panics if the tags are invalid, see [`BuildError`]
+///
+/// when several tags match, the first declared one is returned, see
+/// [`MultiTagBuilder`] for other policies
+pub fn multitag_mode<M: Mode>(tags: &[&[u8]]) -> MultiTag<M> {
+  MultiTagBuilder::new(tags).build().unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// configures a [`MultiTag`]
+#[derive(Clone, Debug)]
+pub struct MultiTagBuilder<'t> {
+  tags: Tags<'t>,
+  policy: MatchPolicy,
+  no_case: bool,
+  delimiters: Option<&'t [u8; 256]>,
+}
+
+impl<'t> MultiTagBuilder<'t> {
+  pub fn new(tags: &'t [&'t [u8]]) -> Self {
+    MultiTagBuilder { tags: Tags::Bytes(tags), policy: MatchPolicy::FirstDeclared, no_case: false, delimiters: None }
+  }
+
+  /// tags with a [`ByteClass`] at each position instead of a byte, like
+  /// `HTTP/1.` followed by [`ByteClass::DIGIT`]
+  ///
+  /// with `no_case`, the classes other than single bytes apply to the
+  /// lowercased input
+  pub fn patterns(patterns: &'t [&'t [ByteClass]]) -> Self {
+    MultiTagBuilder { tags: Tags::Patterns(patterns), policy: MatchPolicy::FirstDeclared, no_case: false, delimiters: None }
+  }
+
+  pub fn policy(mut self, policy: MatchPolicy) -> Self {
+    self.policy = policy;
+    self
+  }
+
+  /// ignores ASCII case, like `tag_no_case`
+  pub fn no_case(mut self, no_case: bool) -> Self {
+    self.no_case = no_case;
+    self
+  }
+
+  /// see [`MultiTag::with_delimiters`]
+  pub fn delimiters(mut self, table: &'t [u8; 256]) -> Self {
+    self.delimiters = Some(table);
+    self
+  }
+
+  pub fn build<M: Mode>(&self) -> Result<MultiTag<M>, BuildError> {
+    validate_tags(self.tags, self.no_case)?;
+
+    let mut groups = Vec::new();
+    let mut start = 0;
+    while start < self.tags.len() {
+        let end = group_end(self.tags, start);
+        groups.push(MasksVec::from(prepare_group(self.tags, start, end, self.no_case)));
+        start = end;
+    }
+
+    let delimiters = self.delimiters.map(Delimiters::from_lookup_table);
+    Ok(MultiTag { groups: Cow::Owned(groups), policy: self.policy, delimiters, mode: PhantomData })
+  }
+
+  /// the parser returns `values[i]` instead of the index `i` of the matched tag
+  pub fn build_values<M: Mode, T: Copy>(&self, values: &[T]) -> Result<MultiTagValues<T, M>, BuildError> {
+    if values.len() != self.tags.len() {
+        return Err(BuildError::ValueCount { tags: self.tags.len(), values: values.len() });
+    }
+
+    Ok(MultiTagValues { tags: self.build()?, values: Cow::Owned(values.to_vec()) })
+  }
+}
+
+/// reason why a list of tags cannot be used by [`MultiTag`]
+///
+/// [`take_until_any`] only returns `EmptyTag` and `DuplicateTag`: its tags
+/// can have any length, and there can be any number of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildError {
+  /// the tag at this index is empty
+  EmptyTag { index: usize },
+  /// the tag at this index is longer than 16 bytes
+  TagTooLong { index: usize, len: usize },
+  /// the tag at index `second` is the same as the one at index `first`,
+  /// ignoring ASCII case with `no_case`, so it can never be returned
+  DuplicateTag { first: usize, second: usize },
+  /// there are more than 255 tags
+  TooManyTags { count: usize },
+  /// [`MultiTagBuilder::build_values`] needs one value per tag
+  ValueCount { tags: usize, values: usize },
+}
 
+impl BuildError {
+  /// description of the error, without the indexes, for the const path
+  pub const fn message(&self) -> &'static str {
+    match self {
+      BuildError::EmptyTag { .. } => "tags must not be empty",
+      BuildError::TagTooLong { .. } => "tags must be at most 16 bytes long",
+      BuildError::DuplicateTag { .. } => "tags must be unique",
+      BuildError::TooManyTags { .. } => "there must be at most 255 tags",
+      BuildError::ValueCount { .. } => "there must be one value per tag",
+    }
+  }
+}
+
+impl std::fmt::Display for BuildError {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      BuildError::EmptyTag { index } => write!(f, "tag {} is empty: {}", index, self.message()),
+      BuildError::TagTooLong { index, len } => write!(f, "tag {} has {} bytes: {}", index, len, self.message()),
+      BuildError::DuplicateTag { first, second } => write!(f, "tag {} is the same as tag {}: {}", second, first, self.message()),
+      BuildError::TooManyTags { count } => write!(f, "{} tags: {}", count, self.message()),
+      BuildError::ValueCount { tags, values } => write!(f, "{} values for {} tags: {}", values, tags, self.message()),
+    }
+  }
+}
+
+impl std::error::Error for BuildError {}
+
+/// checks that the tags can be packed, see [`BuildError`]
+pub const fn validate(strings: &[&[u8]], no_case: bool) -> Result<(), BuildError> {
+    validate_tags(Tags::Bytes(strings), no_case)
+}
+
+const fn validate_tags(tags: Tags, no_case: bool) -> Result<(), BuildError> {
+    // the ids are bytes, and 0xFF means no match
+    if tags.len() > 255 {
+        return Err(BuildError::TooManyTags { count: tags.len() });
+    }
+
+    validate_each(tags, no_case, 16)
+}
+
+/// checks that the tags are not empty, at most `max_len` bytes long, and unique
+const fn validate_each(tags: Tags, no_case: bool, max_len: usize) -> Result<(), BuildError> {
     let mut index = 0usize;
-    let mut s_index = 0usize;
     loop {
-        if s_index == strings.len() {
+        if index == tags.len() {
             break;
         }
 
-        let s = strings[s_index];
+        let len = tags.tag_len(index);
+        if len == 0 {
+            return Err(BuildError::EmptyTag { index });
+        }
+        if len > max_len {
+            return Err(BuildError::TagTooLong { index, len });
+        }
 
-        let mut cmp_index = index;
+        let mut first = 0usize;
         loop {
-            if cmp_index == index+s.len() {
+            if first == index {
                 break;
             }
-
-            cmp[cmp_index] = s[cmp_index - index];
-            cmp_index += 1;
+            if tags_eq(tags, first, index, no_case) {
+                return Err(BuildError::DuplicateTag { first, second: index });
+            }
+            first += 1;
         }
 
-        let mut i = 0usize;
-        loop {
-            if i == s.len() {
-                break;
-            }
-            shuf_mask[index+i] = i as u8;
+        index += 1;
+    }
 
-            i += 1;
+    Ok(())
+}
+
+const fn tags_eq(tags: Tags, a: usize, b: usize, no_case: bool) -> bool {
+    if tags.tag_len(a) != tags.tag_len(b) {
+        return false;
+    }
+
+    let mut i = 0usize;
+    loop {
+        if i == tags.tag_len(a) {
+            return true;
         }
 
-        high_mask |= 1 << index + s.len() - 1;
-        low_mask |= 1 << index;
+        let x = tags.class(a, i).fold_case(no_case);
+        let y = tags.class(b, i).fold_case(no_case);
+        if x.mask != y.mask || x.low != y.low || x.span != y.span {
+            return false;
+        }
+        i += 1;
+    }
+}
 
-        ids[(index + s.len()) - 1] = s_index as u8;
+/// panics with the error's message, so invalid tags fail compilation in a
+/// const context
+const fn check(tags: Tags, no_case: bool) {
+    if let Err(e) = validate_tags(tags, no_case) {
+        panic!("{}", e.message());
+    }
+}
 
-        /*
-        println!("cmp: {:x?}", cmp);
-        println!("shuf_mask: {:x?}", shuf_mask);
-        print_u32("high mask", high_mask);
-        print_u32("low mask", low_mask);
-        */
+/// set of bytes accepted at one position of a pattern, see
+/// [`MultiTagBuilder::patterns`]
+///
+/// a byte `b` is in the class if `(b & mask) - low`, wrapping, is at most
+/// `span`, so a group of patterns is still checked with one vector
+/// comparison
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteClass {
+  mask: u8,
+  low: u8,
+  span: u8,
+}
 
-        index += s.len();
-        s_index += 1;
+impl ByteClass {
+  pub const ANY: ByteClass = ByteClass { mask: 0, low: 0, span: 0 };
+  pub const DIGIT: ByteClass = ByteClass::range(b'0', b'9');
+
+  pub const fn byte(b: u8) -> Self {
+    ByteClass { mask: 0xFF, low: b, span: 0 }
+  }
+
+  /// the bytes from `low` to `high`, included
+  pub const fn range(low: u8, high: u8) -> Self {
+    if low > high {
+        panic!("the range must not be empty");
     }
+    ByteClass { mask: 0xFF, low, span: high - low }
+  }
 
-    //println!("cmpstring: {}", std::str::from_utf8(&cmp[..]).unwrap());
-    //println!("ids: {:?}", ids);
+  /// the bytes with the same bits as `value` where `mask` is set
+  pub const fn masked(value: u8, mask: u8) -> Self {
+    ByteClass { mask, low: value & mask, span: 0 }
+  }
+
+  /// `a` or `b`: they must be consecutive, or differ by one bit
+  pub const fn either(a: u8, b: u8) -> Self {
+    let (low, high) = if a < b { (a, b) } else { (b, a) };
+    if high - low <= 1 {
+        ByteClass::range(low, high)
+    } else if (a ^ b).count_ones() == 1 {
+        ByteClass::masked(a, !(a ^ b))
+    } else {
+        panic!("the bytes must be consecutive or differ by one bit");
+    }
+  }
+
+  /// the pattern matching exactly `tag`
+  pub const fn literal<const N: usize>(tag: &[u8; N]) -> [ByteClass; N] {
+    let mut classes = [ByteClass::ANY; N];
+    let mut i = 0usize;
+    loop {
+        if i == N {
+            break;
+        }
+        classes[i] = ByteClass::byte(tag[i]);
+        i += 1;
+    }
+
+    classes
+  }
+
+  pub const fn matches(&self, b: u8) -> bool {
+    (b & self.mask).wrapping_sub(self.low) <= self.span
+  }
+
+  const fn is_byte(&self) -> bool {
+    self.mask == 0xFF && self.span == 0
+  }
 
-    Masks { cmp, shuf_mask, high_mask, low_mask, ids }
+  /// the input is lowercased with `no_case`, so single bytes are too
+  const fn fold_case(self, no_case: bool) -> Self {
+    if no_case && self.is_byte() {
+        ByteClass::byte(to_ascii_lowercase_scalar(self.low))
+    } else {
+        self
+    }
+  }
 }
 
-pub struct MasksVec {
-  cmp: __m256i,
-  shuf_mask: __m256i,
-  high_mask: u32,
-  low_mask: u32,
-  ids: [u8; 32],
+/// the tags given to [`MultiTagBuilder`]
+#[derive(Clone, Copy, Debug)]
+enum Tags<'a> {
+  Bytes(&'a [&'a [u8]]),
+  Patterns(&'a [&'a [ByteClass]]),
 }
 
-impl MasksVec {
-    pub fn from(m: Masks) -> Self {
-        let Masks { cmp, shuf_mask, high_mask, low_mask, ids } = m;
-        let cmp = load(&cmp[..]);
-        let shuf_mask = load(&shuf_mask[..]);
+impl<'a> Tags<'a> {
+    const fn len(&self) -> usize {
+        match self {
+            Tags::Bytes(tags) => tags.len(),
+            Tags::Patterns(patterns) => patterns.len(),
+        }
+    }
+
+    const fn tag_len(&self, index: usize) -> usize {
+        match self {
+            Tags::Bytes(tags) => tags[index].len(),
+            Tags::Patterns(patterns) => patterns[index].len(),
+        }
+    }
 
-        MasksVec { cmp, shuf_mask, high_mask, low_mask, ids }
+    const fn class(&self, index: usize, position: usize) -> ByteClass {
+        match self {
+            Tags::Bytes(tags) => ByteClass::byte(tags[index][position]),
+            Tags::Patterns(patterns) => patterns[index][position],
+        }
     }
 }
 
-fn avx(i: &[u8]) -> () {
-    println!("input:\n{}", i.to_hex(16));
-    let input = load16(i);
-    let d = dump(input);
-    println!("dumped:\n{}", &d.to_hex(16));
+/// parser built by [`multitag_values`] or [`MultiTagBuilder::build_values`]
+#[derive(Clone)]
+pub struct MultiTagValues<T: Copy + 'static, M = Streaming> {
+  tags: MultiTag<M>,
+  values: Cow<'static, [T]>,
+}
 
-    let strings = [&b"Acce"[..], &b"ConA"[..], &b"Date"[..], &b"Cont"[..], &b"Forw"[..], &b"Host"[..], &b"User"[..], &b"Upgr"[..]];
-    let Masks { cmp, shuf_mask, high_mask, low_mask, ids } = prepare(&strings[..]);
-    let cmp_mask = load(&cmp);
-    print_hex("cmp_mask", cmp_mask);
-    let shuf_mask = load(&shuf_mask[..]);
-    let shuffled = unsafe { _mm256_shuffle_epi8(input, shuf_mask) };
-    print_hex("shuffled", shuffled);
+impl<T: Copy + 'static, M: Mode> MultiTagValues<T, M> {
+  /// returns the value of the matched tag
+  pub fn parse<'a, Error: ParseError<&'a [u8]>>(&self, i: &'a [u8]) -> IResult<&'a [u8], T, Error> {
+    let (rest, (idx, _)) = self.tags.parse(i)?;
+    Ok((rest, self.values[idx]))
+  }
 
-    let cmpres = unsafe { _mm256_cmpeq_epi8(shuffled, cmp_mask) };
-    print_hex("cmpres", cmpres);
-    //print_bin("cmpres", cmpres);
+  /// same as `parse`, short inputs are loaded directly from the padding
+  pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(&self, i: PaddedInput<'a>) -> IResult<PaddedInput<'a>, T, Error> {
+    let (rest, (idx, _)) = self.tags.parse_padded(i)?;
+    Ok((rest, self.values[idx]))
+  }
 
-    let maskres = unsafe { _mm256_movemask_epi8(cmpres) };
-    println!("maskres: LE {:x?}, BE: {:x?}", maskres.to_le_bytes(), maskres.to_be_bytes());
-    print_u32("maskres_u32", maskres as u32);
-    print_u32("hi", high_mask);
-    print_u32("low", low_mask);
-    let tmp_mask = maskres as u32 & !high_mask;
-    print_u32("mask & !hi", tmp_mask);
-    let (tmp_mask2, _) = tmp_mask.overflowing_add(low_mask);
-    print_u32("(mask & !hi) + low", tmp_mask2);
-    let tmp_mask3 = tmp_mask2 & maskres as u32;
-    print_u32("((mask & !hi) + low) & mask", tmp_mask3);
-    let res = tmp_mask3 & high_mask;
-    print_u32("((mask & !hi) + low) & mask & hi", res);
+  /// parser using groups built at compile time, see [`MultiTag::from_groups`]
+  pub const fn from_groups(groups: &'static [MasksVec], values: &'static [T]) -> Self {
+    MultiTagValues { tags: MultiTag::from_groups(groups), values: Cow::Borrowed(values) }
+  }
 
-    let cnt = unsafe { _lzcnt_u32(res) };
-    println!("lzcnt: {}", cnt);
+  pub const fn with_policy(mut self, policy: MatchPolicy) -> Self {
+    self.tags.policy = policy;
+    self
+  }
 
-    println!("found? {:?}", std::str::from_utf8(strings[ids[(31 - cnt) as usize] as usize]).unwrap());
+  /// see [`MultiTag::with_delimiters`]
+  pub const fn with_delimiters(mut self, table: &[u8; 256]) -> Self {
+    self.tags.delimiters = Some(Delimiters::from_lookup_table(table));
+    self
+  }
 }
 
-fn load(i: &[u8]) -> __m256i {
-    unsafe {
-        _mm256_loadu2_m128i(
-            (&i[16..]).as_ptr() as *const _,
-            i.as_ptr() as *const _,
-        )
-    }
+impl<'a, T: Copy + 'static, M: Mode, Error: ParseError<&'a [u8]>> Parser<&'a [u8], T, Error> for MultiTagValues<T, M> {
+  fn parse(&mut self, i: &'a [u8]) -> IResult<&'a [u8], T, Error> {
+    MultiTagValues::parse(self, i)
+  }
 }
 
-pub fn load16(i: &[u8]) -> __m256i {
-    unsafe {
-        _mm256_loadu2_m128i(
-            i.as_ptr() as *const _,
-            i.as_ptr() as *const _)
-    }
+impl<'a, T: Copy + 'static, M: Mode, Error: ParseError<PaddedInput<'a>>> Parser<PaddedInput<'a>, T, Error> for MultiTagValues<T, M> {
+  fn parse(&mut self, i: PaddedInput<'a>) -> IResult<PaddedInput<'a>, T, Error> {
+    MultiTagValues::parse_padded(self, i)
+  }
 }
 
-fn dump(i: __m256i) -> [u8; 32] {
-    let mut res = [0u8; 32];
+/// same as [`multitag`], returning the value associated with the matched tag
+pub fn multitag_values_mode<M: Mode, T: Copy>(pairs: &[(&[u8], T)]) -> MultiTagValues<T, M> {
+  let tags: Vec<&[u8]> = pairs.iter().map(|(tag, _)| *tag).collect();
+  let values: Vec<T> = pairs.iter().map(|(_, value)| *value).collect();
+  MultiTagBuilder::new(&tags).build_values(&values).unwrap_or_else(|e| panic!("{}", e))
+}
 
-    unsafe {
-        _mm256_storeu2_m128i(
-            (&mut res[16..]).as_mut_ptr() as *mut _,
-            res.as_mut_ptr() as *mut _,
-            i
-        );
+/// declares an enum with one variant per tag, and the tables to build a
+/// [`MultiTagValues`] returning it
+///
+/// the enum derives `Clone`, `Copy`, `Debug`, `PartialEq`, `Eq` and `Hash`,
+/// and gets the `TAGS` and `VALUES` constants, in declaration order, and a
+/// `tag` method returning the variant's tag
+///
+/// `multitag_enum! { pub enum HeaderName { Host = b"Host", ContentLength = b"Content-Length" } }`
+/// can then be used with `MultiTagBuilder::new(HeaderName::TAGS).build_values(HeaderName::VALUES)`
+#[macro_export]
+macro_rules! multitag_enum (
+  ($(#[$attr:meta])* $vis:vis enum $name:ident { $($(#[$variant_attr:meta])* $variant:ident = $tag:expr),+ $(,)? }) => (
+    $(#[$attr])*
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    $vis enum $name {
+      $($(#[$variant_attr])* $variant),+
     }
 
-    res
-}
+    impl $name {
+      pub const TAGS: &'static [&'static [u8]] = &[$($tag),+];
+      pub const VALUES: &'static [$name] = &[$($name::$variant),+];
 
-pub fn print_hex(prefix: &str, i: __m256i) {
-    println!("{}:\n{}", prefix, &(dump(i)).to_hex(16));
-}
+      pub fn tag(&self) -> &'static [u8] {
+        Self::TAGS[*self as usize]
+      }
+    }
+  );
+);
 
-fn print_bin(prefix: &str, i: __m256i) {
-    let d = dump(i);
+/// declares a `static` [`MultiTag`] or [`MultiTagValues`] with its groups
+/// prepared at compile time
+///
+/// `static_multitag!(pub static METHODS: MultiTag<Complete> = [b"GET", b"POST"]);`
+/// builds a `MultiTag`, while `[b"GET" => Method::Get, b"POST" => Method::Post]`
+/// builds a `MultiTagValues<Method, _>`. The list can be preceded by
+/// `no_case` and followed by `.with_policy(MatchPolicy::Longest)`
+#[macro_export]
+macro_rules! static_multitag (
+  (@prepare [] $tags:expr) => ($crate::avx::prepare_groups($tags));
+  (@prepare [no_case] $tags:expr) => ($crate::avx::prepare_groups_no_case($tags));
+  ($(#[$attr:meta])* $vis:vis static $name:ident : $ty:ty = $($no_case:ident)? [$($tag:expr => $value:expr),+ $(,)?] $(.$method:ident($($arg:expr),*))* $(;)?) => (
+    $(#[$attr])*
+    $vis static $name: $ty = {
+      const TAGS: &[&[u8]] = &[$($tag),+];
+      static GROUPS: [$crate::avx::MasksVec; $crate::avx::group_count(TAGS)] = $crate::static_multitag!(@prepare [$($no_case)?] TAGS);
+      $crate::avx::MultiTagValues::from_groups(&GROUPS, &[$($value),+])$(.$method($($arg),*))*
+    };
+  );
+  ($(#[$attr:meta])* $vis:vis static $name:ident : $ty:ty = $($no_case:ident)? [$($tag:expr),+ $(,)?] $(.$method:ident($($arg:expr),*))* $(;)?) => (
+    $(#[$attr])*
+    $vis static $name: $ty = {
+      const TAGS: &[&[u8]] = &[$($tag),+];
+      static GROUPS: [$crate::avx::MasksVec; $crate::avx::group_count(TAGS)] = $crate::static_multitag!(@prepare [$($no_case)?] TAGS);
+      $crate::avx::MultiTag::from_groups(&GROUPS)$(.$method($($arg),*))*
+    };
+  );
+);
 
-    let mut s = String::new();
-    for byte in d.iter() {
-        for i in 0..8 {
-            let c = if (byte & (1 << 7 - i)) != 0 {
-                '1'
-            } else {
-                '_'
-            };
-            s.push(c);
+/// returns a mask with the bit of each position of the packed tags set if
+/// the input byte at this position of the tag is equal to the tag's byte
+///
+/// uses AVX2 if available, then SSSE3, otherwise falls back to
+/// `eq_mask_scalar`. They all return the same mask
+///
+/// `input` must contain at least 16 bytes
+#[inline(always)]
+fn eq_mask(m: &MasksVec, input: &[u8]) -> u32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_avx2() {
+            return unsafe { eq_mask_avx2(m, input) };
+        } else if crate::cpu::has_ssse3() {
+            return unsafe { eq_mask_ssse3(m, input) };
         }
-        s.push(' ');
     }
-    println!("{}:\n{}", prefix, s);
+
+    eq_mask_scalar(m, input)
 }
 
-fn print_u32(suffix: &str, i: u32) {
-    let d = i.to_le_bytes();
-    println!("u32 bytes:{:x?}", d);
-    let mut s = String::new();
-    for idx in 0..32 {
-        s.push(if ((i >> idx) & 1) == 1 { '1' } else { '_' });
-    }
-    println!("{}\t{}", s, suffix);
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn eq_mask_avx2(m: &MasksVec, i: &[u8]) -> u32 {
+    eq_mask_vector_avx2(m, load16(i))
 }
 
-fn push_byte(s: &mut String, byte: u8) {
-    for i in 0..8 {
-        let c = if (byte & (1 << (7-i))) != 0 {
-            '1'
-        } else {
-            '_'
-        };
-        s.push(c);
+/// `eq_mask_avx2` over the 16 bytes in both lanes of `input`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline]
+unsafe fn eq_mask_vector_avx2(m: &MasksVec, input: __m256i) -> u32 {
+    let mut shuffled = _mm256_shuffle_epi8(input, m.shuf_mask);
+    if m.no_case {
+        shuffled = to_ascii_lowercase_avx2(shuffled);
     }
+    let cmpres = if m.classes {
+        // see `ByteClass`: the difference is unchanged by the minimum if it
+        // is at most the span
+        let diff = _mm256_sub_epi8(_mm256_and_si256(shuffled, m.and_mask), m.cmp);
+        _mm256_cmpeq_epi8(_mm256_min_epu8(diff, m.span), diff)
+    } else {
+        _mm256_cmpeq_epi8(shuffled, m.cmp)
+    };
+    _mm256_movemask_epi8(cmpres) as u32
 }
 
-fn print_u32_be(suffix: &str, i: u32) {
-    let d = i.to_be_bytes();
-    println!("u32 bytes:{:x?}", d);
-    let mut s = String::new();
-    push_byte(&mut s, d[3]);
-    push_byte(&mut s, d[2]);
-    push_byte(&mut s, d[1]);
-    push_byte(&mut s, d[0]);
-    println!("{}\t{}", s, suffix);
+/// 128 bits version of `eq_mask_avx2`
+///
+/// `_mm256_shuffle_epi8` shuffles each 128 bits lane separately, and both
+/// lanes get the same 16 input bytes, so the group is processed as two
+/// groups of 16 packed bytes, whose masks are the low and high halves of
+/// the result
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+unsafe fn eq_mask_ssse3(m: &MasksVec, i: &[u8]) -> u32 {
+    let input = _mm_loadu_si128(i.as_ptr() as *const _);
+    let [shuf_low, shuf_high] = halves(m.shuf_mask);
+    let [cmp_low, cmp_high] = halves(m.cmp);
+    let [and_low, and_high] = halves(m.and_mask);
+    let [span_low, span_high] = halves(m.span);
+
+    let low = compare_ssse3(m, _mm_shuffle_epi8(input, shuf_low), cmp_low, and_low, span_low);
+    let high = compare_ssse3(m, _mm_shuffle_epi8(input, shuf_high), cmp_high, and_high, span_high);
+    low | (high << 16)
+}
+
+/// compares one half of the group, see `eq_mask_vector_avx2`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+unsafe fn compare_ssse3(m: &MasksVec, mut shuffled: __m128i, cmp: __m128i, and_mask: __m128i, span: __m128i) -> u32 {
+    if m.no_case {
+        shuffled = to_ascii_lowercase_sse2(shuffled);
+    }
+    let cmpres = if m.classes {
+        let diff = _mm_sub_epi8(_mm_and_si128(shuffled, and_mask), cmp);
+        _mm_cmpeq_epi8(_mm_min_epu8(diff, span), diff)
+    } else {
+        _mm_cmpeq_epi8(shuffled, cmp)
+    };
+    _mm_movemask_epi8(cmpres) as u32
 }
 
-fn push_byte_be(s: &mut String, byte: u8) {
-    for i in 0..8 {
-        let c = if (byte & (1 << (7-i))) != 0 {
-            '1'
+/// portable version of `eq_mask_avx2`: the shuffle is done byte per byte
+/// over the first 16 bytes of `i`
+fn eq_mask_scalar(m: &MasksVec, i: &[u8]) -> u32 {
+    let (cmp, and_mask, span) = (dump(m.cmp), dump(m.and_mask), dump(m.span));
+
+    let mut maskres = 0u32;
+    for (j, &shuf) in dump(m.shuf_mask).iter().enumerate() {
+        // same behaviour as `_mm256_shuffle_epi8`: the high bit sets the
+        // result to zero, and only the low 4 bits are used as index
+        let shuffled = if shuf & 0x80 != 0 { 0 } else { i[(shuf & 0x0F) as usize] };
+        let shuffled = if m.no_case { to_ascii_lowercase_scalar(shuffled) } else { shuffled };
+        let matched = if m.classes {
+            ByteClass { mask: and_mask[j], low: cmp[j], span: span[j] }.matches(shuffled)
         } else {
-            '_'
+            shuffled == cmp[j]
         };
-        s.push(c);
+        if matched {
+            maskres |= 1 << j;
+        }
     }
+
+    maskres
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// parser built by [`take_until_any`]
+///
+/// like the Teddy algorithm, the tags are spread in 8 buckets, and the first
+/// bytes of the input positions are looked up in nibble tables to get the
+/// buckets of the tags that could start there. Only those candidates are
+/// then compared with the tags
+#[derive(Clone)]
+pub struct TakeUntilAny<M = Streaming> {
+  tags: Vec<Vec<u8>>,
+  // tag indexes of each bucket, in declaration order
+  buckets: [Vec<usize>; 8],
+  // for each byte of the fingerprint, the buckets with a tag having this
+  // low or high nibble at this position
+  low_nibbles: [[u8; 16]; 3],
+  high_nibbles: [[u8; 16]; 3],
+  // number of bytes of the fingerprint: the length of the shortest tag, at most 3
+  fingerprint: usize,
+  mode: PhantomData<M>,
+}
 
-    #[test]
-    fn avx_test() {
-        avx(&b"Content-Length: 1234\r\nHost: hello.com"[..]);
+impl<M: Mode> TakeUntilAny<M> {
+  /// returns the input before the first tag, and the index of the tag
+  ///
+  /// when several tags start at the same position, the first declared one
+  /// is returned. In streaming mode, a tag that could start before the
+  /// first match, but runs past the end of the input, returns
+  /// `Err::Incomplete`
+  pub fn parse<'a, Error: ParseError<&'a [u8]>>(&self, i: &'a [u8]) -> IResult<&'a [u8], (&'a [u8], usize), Error> {
+    match self.scan(i) {
+        Scan::Found { position, idx } => Ok((&i[position..], (&i[..position], idx))),
+        Scan::Incomplete(needed) => Err(Err::Incomplete(Needed::new(needed))),
+        Scan::NotFound if M::STREAMING => Err(Err::Incomplete(Needed::Unknown)),
+        Scan::NotFound => Err(Err::Error(Error::from_error_kind(i, ErrorKind::TakeUntil))),
+    }
+  }
 
-        panic!();
+  /// uses AVX2 if available, then SSSE3, otherwise falls back to `scan_from`
+  #[inline(always)]
+  fn scan(&self, input: &[u8]) -> Scan {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_avx2() {
+            return unsafe { self.scan_avx2(input) };
+        } else if crate::cpu::has_ssse3() {
+            return unsafe { self.scan_ssse3(input) };
+        }
     }
 
-    #[test]
-    fn prepare_test() {
-        let strings = [&b"Acce"[..], &b"Cont"[..], &b"Date"[..], &b"ConA"[..], &b"Forw"[..], &b"Host"[..], &b"User"[..], &b"Up34"[..]];
-        prepare(&strings[..]);
-        panic!();
+    self.scan_from(input, 0)
+  }
+
+  /// buckets of the tags whose fingerprint matches the input at `position`,
+  /// the bytes past the end of the input match any tag
+  #[inline(always)]
+  fn candidates(&self, input: &[u8], position: usize) -> u8 {
+    let mut buckets = 0xFFu8;
+    for k in 0..self.fingerprint {
+        if let Some(&c) = input.get(position + k) {
+            buckets &= self.low_nibbles[k][(c & 0x0F) as usize] & self.high_nibbles[k][(c >> 4) as usize];
+        }
+    }
+
+    buckets
+  }
+
+  /// compares the tags of `buckets` with the input at `position`
+  fn verify(&self, input: &[u8], position: usize, buckets: u8) -> Option<Scan> {
+    let rest = &input[position..];
+    let mut found: Option<usize> = None;
+    let mut partial: Option<(usize, usize)> = None;
+
+    let mut bits = buckets;
+    while bits != 0 {
+        for &idx in self.buckets[bits.trailing_zeros() as usize].iter() {
+            let tag = &self.tags[idx];
+            // the buckets are not in declaration order
+            if rest.starts_with(tag) {
+                found = Some(found.map_or(idx, |found| found.min(idx)));
+            } else if M::STREAMING && rest.len() < tag.len() && tag.starts_with(rest) {
+                let missing = tag.len() - rest.len();
+                partial = Some(partial.map_or((idx, missing), |partial| partial.min((idx, missing))));
+            }
+        }
+        bits &= bits - 1;
+    }
+
+    match (found, partial) {
+        (Some(idx), Some((partial, _))) if idx < partial => Some(Scan::Found { position, idx }),
+        (_, Some((_, needed))) => Some(Scan::Incomplete(needed)),
+        (Some(idx), None) => Some(Scan::Found { position, idx }),
+        (None, None) => None,
+    }
+  }
+
+  /// portable version, also used for the end of the input
+  fn scan_from(&self, input: &[u8], start: usize) -> Scan {
+    for position in start..input.len() {
+        let buckets = self.candidates(input, position);
+        if buckets != 0 {
+            if let Some(scan) = self.verify(input, position, buckets) {
+                return scan;
+            }
+        }
+    }
+
+    Scan::NotFound
+  }
+
+  /// checks the candidates of the block at `start`, one bit per position in `mask`
+  #[cfg(target_arch = "x86_64")]
+  #[inline(always)]
+  fn verify_block(&self, input: &[u8], start: usize, mut mask: u32, buckets: &[u8]) -> Option<Scan> {
+    while mask != 0 {
+        let j = mask.trailing_zeros() as usize;
+        if let Some(scan) = self.verify(input, start + j, buckets[j]) {
+            return Some(scan);
+        }
+        mask &= mask - 1;
+    }
+
+    None
+  }
+
+  #[cfg(target_arch = "x86_64")]
+  #[target_feature(enable = "avx2")]
+  unsafe fn scan_avx2(&self, input: &[u8]) -> Scan {
+    let nibble = _mm256_set1_epi8(0x0F);
+    let zero = _mm256_setzero_si256();
+    let mut low = [zero; 3];
+    let mut high = [zero; 3];
+    for k in 0..self.fingerprint {
+        low[k] = _mm256_broadcastsi128_si256(_mm_loadu_si128(self.low_nibbles[k].as_ptr() as *const __m128i));
+        high[k] = _mm256_broadcastsi128_si256(_mm_loadu_si128(self.high_nibbles[k].as_ptr() as *const __m128i));
+    }
+
+    let mut i = 0usize;
+    while i + self.fingerprint - 1 + 32 <= input.len() {
+        let mut buckets = _mm256_set1_epi8(-1);
+        for k in 0..self.fingerprint {
+            let v = _mm256_loadu_si256(input.as_ptr().add(i + k) as *const __m256i);
+            let lo = _mm256_shuffle_epi8(low[k], _mm256_and_si256(v, nibble));
+            let hi = _mm256_shuffle_epi8(high[k], _mm256_and_si256(_mm256_srli_epi16(v, 4), nibble));
+            buckets = _mm256_and_si256(buckets, _mm256_and_si256(lo, hi));
+        }
+
+        let mask = !(_mm256_movemask_epi8(_mm256_cmpeq_epi8(buckets, zero)) as u32);
+        if mask != 0 {
+            if let Some(scan) = self.verify_block(input, i, mask, &dump(buckets)) {
+                return scan;
+            }
+        }
+
+        i += 32;
+    }
+
+    self.scan_from(input, i)
+  }
+
+  #[cfg(target_arch = "x86_64")]
+  #[target_feature(enable = "ssse3")]
+  unsafe fn scan_ssse3(&self, input: &[u8]) -> Scan {
+    let nibble = _mm_set1_epi8(0x0F);
+    let zero = _mm_setzero_si128();
+    let mut low = [zero; 3];
+    let mut high = [zero; 3];
+    for k in 0..self.fingerprint {
+        low[k] = _mm_loadu_si128(self.low_nibbles[k].as_ptr() as *const __m128i);
+        high[k] = _mm_loadu_si128(self.high_nibbles[k].as_ptr() as *const __m128i);
+    }
+
+    let mut i = 0usize;
+    while i + self.fingerprint - 1 + 16 <= input.len() {
+        let mut buckets = _mm_set1_epi8(-1);
+        for k in 0..self.fingerprint {
+            let v = _mm_loadu_si128(input.as_ptr().add(i + k) as *const __m128i);
+            let lo = _mm_shuffle_epi8(low[k], _mm_and_si128(v, nibble));
+            let hi = _mm_shuffle_epi8(high[k], _mm_and_si128(_mm_srli_epi16(v, 4), nibble));
+            buckets = _mm_and_si128(buckets, _mm_and_si128(lo, hi));
+        }
+
+        let mask = !(_mm_movemask_epi8(_mm_cmpeq_epi8(buckets, zero)) as u32) & 0xFFFF;
+        if mask != 0 {
+            let bytes = std::mem::transmute::<__m128i, [u8; 16]>(buckets);
+            if let Some(scan) = self.verify_block(input, i, mask, &bytes) {
+                return scan;
+            }
+        }
+
+        i += 16;
+    }
+
+    self.scan_from(input, i)
+  }
+}
+
+/// result of the `TakeUntilAny` scans
+#[derive(Debug, PartialEq)]
+enum Scan {
+  Found { position: usize, idx: usize },
+  /// number of missing bytes
+  Incomplete(usize),
+  NotFound,
+}
+
+impl<'a, M: Mode, Error: ParseError<&'a [u8]>> Parser<&'a [u8], (&'a [u8], usize), Error> for TakeUntilAny<M> {
+  fn parse(&mut self, i: &'a [u8]) -> IResult<&'a [u8], (&'a [u8], usize), Error> {
+    TakeUntilAny::parse(self, i)
+  }
+}
+
+/// returns the input up to the first occurrence of any of the tags, and the
+/// index of this tag
+///
+/// the tags must not be empty, and must be unique, otherwise this panics,
+/// see [`BuildError`]. Unlike [`multitag`], they can be longer than 16 bytes,
+/// and there can be more than 255 of them. The scan is faster with a few
+/// dozen tags at most, and tags of at least 3 bytes
+pub fn take_until_any_mode<M: Mode>(tags: &[&[u8]]) -> TakeUntilAny<M> {
+  if let Err(e) = validate_each(Tags::Bytes(tags), false, usize::MAX) {
+      panic!("{}", e);
+  }
+
+  let fingerprint = tags.iter().map(|tag| tag.len()).min().unwrap_or(1).min(3);
+  let mut buckets: [Vec<usize>; 8] = Default::default();
+  let mut low_nibbles = [[0u8; 16]; 3];
+  let mut high_nibbles = [[0u8; 16]; 3];
+  for (idx, tag) in tags.iter().enumerate() {
+      let bucket = idx % 8;
+      buckets[bucket].push(idx);
+      for (k, &c) in tag[..fingerprint].iter().enumerate() {
+          low_nibbles[k][(c & 0x0F) as usize] |= 1 << bucket;
+          high_nibbles[k][(c >> 4) as usize] |= 1 << bucket;
+      }
+  }
+
+  TakeUntilAny {
+    tags: tags.iter().map(|tag| tag.to_vec()).collect(),
+    buckets,
+    low_nibbles,
+    high_nibbles,
+    fingerprint,
+    mode: PhantomData,
+  }
+}
+
+// the functions at the root of the module keep nom's streaming behaviour,
+// the `_mode` versions take the mode as a type parameter
+pub use self::streaming::*;
+
+mode_modules! {
+    fn multitag[](tags: &[&[u8]]) -> MultiTag[] = multitag_mode;
+    fn multitag_values[T: Copy](pairs: &[(&[u8], T)]) -> MultiTagValues[T] = multitag_values_mode;
+    fn take_until_any[](tags: &[&[u8]]) -> TakeUntilAny[] = take_until_any_mode;
+}
+
+/// id of the tag at the start of `input`, or 0xFF
+///
+/// like [`MasksVec::classify`], the first declared of the matching tags
+/// is returned
+///
+/// # Safety
+///
+/// the CPU must support AVX2, see `cpu::has_avx2`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+pub unsafe fn process(m: &MasksVec, input: __m256i) -> u8 {
+    let maskres = eq_mask_vector_avx2(m, input);
+    let res = m.ends(maskres);
+
+    if res != 0 {
+        m.ids[res.trailing_zeros() as usize]
+    } else {
+        0xFFu8
+    }
+}
+
+#[derive(Clone)]
+pub struct Masks {
+  cmp: [u8; 32],
+  shuf_mask: [u8; 32],
+  high_mask: u32,
+  low_mask: u32,
+  ids: [u8; 32],
+  // length of the tag ending at each position
+  lens: [u8; 32],
+  // `cmp` is lowercased, and the input must be too
+  no_case: bool,
+  // the `ByteClass` of each position is `and_mask`, `cmp` and `span`
+  and_mask: [u8; 32],
+  span: [u8; 32],
+  // some positions are not single bytes
+  classes: bool,
+}
+
+/// packs all the strings in one group, they must fit in 32 bytes
+pub const fn prepare(strings: &[&[u8]]) -> Masks {
+    let tags = Tags::Bytes(strings);
+    check(tags, false);
+    if group_end(tags, 0) != strings.len() {
+        panic!("the tags must fit in 32 bytes, see prepare_groups");
+    }
+
+    prepare_group(tags, 0, strings.len(), false)
+}
+
+/// packs `tags[start..end]`, the ids being indexes in `tags`
+///
+/// with `no_case`, the single bytes are lowercased
+const fn prepare_group(tags: Tags, start: usize, end: usize, no_case: bool) -> Masks {
+    let mut cmp = [0u8; 32];
+    let mut and_mask = [0xFFu8; 32];
+    let mut span = [0u8; 32];
+    let mut classes = false;
+    let mut shuf_mask = [0u8; 32];
+    let mut high_mask = 0u32;
+    let mut low_mask = 0u32;
+    let mut ids = [0xFFu8; 32];
+    let mut lens = [0u8; 32];
+
+    let mut index = 0usize;
+    let mut s_index = start;
+    loop {
+        if s_index == end {
+            break;
+        }
+
+        let len = tags.tag_len(s_index);
+
+        let mut cmp_index = index;
+        loop {
+            if cmp_index == index+len {
+                break;
+            }
+
+            let class = tags.class(s_index, cmp_index - index).fold_case(no_case);
+            cmp[cmp_index] = class.low;
+            and_mask[cmp_index] = class.mask;
+            span[cmp_index] = class.span;
+            classes |= !class.is_byte();
+            cmp_index += 1;
+        }
+
+        let mut i = 0usize;
+        loop {
+            if i == len {
+                break;
+            }
+            shuf_mask[index+i] = i as u8;
+
+            i += 1;
+        }
+
+        high_mask |= 1 << (index + len - 1);
+        low_mask |= 1 << index;
+
+        ids[(index + len) - 1] = s_index as u8;
+        lens[(index + len) - 1] = len as u8;
+
+        /*
+        println!("cmp: {:x?}", cmp);
+        println!("shuf_mask: {:x?}", shuf_mask);
+        print_u32("high mask", high_mask);
+        print_u32("low mask", low_mask);
+        */
+
+        index += len;
+        s_index += 1;
+    }
+
+    //println!("cmpstring: {}", std::str::from_utf8(&cmp[..]).unwrap());
+    //println!("ids: {:?}", ids);
+
+    Masks { cmp, shuf_mask, high_mask, low_mask, ids, lens, no_case, and_mask, span, classes }
+}
+
+/// end of the group starting at `tags[start]`: the following tags are
+/// added while they fit in 32 bytes
+const fn group_end(tags: Tags, start: usize) -> usize {
+    let mut len = 0usize;
+    let mut end = start;
+    loop {
+        if end == tags.len() {
+            break;
+        }
+        if tags.tag_len(end) > 16 {
+            panic!("tags must be at most 16 bytes long");
+        }
+        if len + tags.tag_len(end) > 32 {
+            break;
+        }
+
+        len += tags.tag_len(end);
+        end += 1;
+    }
+
+    end
+}
+
+/// number of groups [`prepare_groups`] needs for `strings`
+pub const fn group_count(strings: &[&[u8]]) -> usize {
+    count_groups(Tags::Bytes(strings))
+}
+
+/// number of groups [`prepare_pattern_groups`] needs for `patterns`
+pub const fn pattern_group_count(patterns: &[&[ByteClass]]) -> usize {
+    count_groups(Tags::Patterns(patterns))
+}
+
+const fn count_groups(tags: Tags) -> usize {
+    check(tags, false);
+
+    let mut count = 0usize;
+    let mut start = 0usize;
+    loop {
+        if start == tags.len() {
+            break;
+        }
+
+        start = group_end(tags, start);
+        count += 1;
+    }
+
+    count
+}
+
+/// packs the strings in `N` groups, `N` must be [`group_count`]`(strings)`
+pub const fn prepare_groups<const N: usize>(strings: &[&[u8]]) -> [MasksVec; N] {
+    pack_groups(Tags::Bytes(strings), false)
+}
+
+/// same as [`prepare_groups`], ignoring ASCII case
+pub const fn prepare_groups_no_case<const N: usize>(strings: &[&[u8]]) -> [MasksVec; N] {
+    pack_groups(Tags::Bytes(strings), true)
+}
+
+/// packs the patterns in `N` groups, `N` must be
+/// [`pattern_group_count`]`(patterns)`
+pub const fn prepare_pattern_groups<const N: usize>(patterns: &[&[ByteClass]]) -> [MasksVec; N] {
+    pack_groups(Tags::Patterns(patterns), false)
+}
+
+const fn pack_groups<const N: usize>(tags: Tags, no_case: bool) -> [MasksVec; N] {
+    check(tags, no_case);
+
+    const EMPTY: MasksVec = MasksVec::from(prepare(&[]));
+    let mut groups = [EMPTY; N];
+
+    let mut start = 0usize;
+    let mut i = 0usize;
+    loop {
+        if start == tags.len() {
+            break;
+        }
+
+        let end = group_end(tags, start);
+        groups[i] = MasksVec::from(prepare_group(tags, start, end, no_case));
+        start = end;
+        i += 1;
+    }
+
+    if i != N {
+        panic!("N must be the number of groups");
+    }
+
+    groups
+}
+
+/// a group of tags prepared by [`prepare`], with the comparison and shuffle
+/// masks already in vectors
+#[derive(Clone, Copy)]
+pub struct MasksVec {
+  cmp: Vector,
+  shuf_mask: Vector,
+  high_mask: u32,
+  low_mask: u32,
+  ids: [u8; 32],
+  lens: [u8; 32],
+  no_case: bool,
+  and_mask: Vector,
+  span: Vector,
+  classes: bool,
+}
+
+impl MasksVec {
+    pub const fn from(m: Masks) -> Self {
+        let Masks { cmp, shuf_mask, high_mask, low_mask, ids, lens, no_case, and_mask, span, classes } = m;
+        let cmp = vector(cmp);
+        let shuf_mask = vector(shuf_mask);
+        let and_mask = vector(and_mask);
+        let span = vector(span);
+
+        MasksVec { cmp, shuf_mask, high_mask, low_mask, ids, lens, no_case, and_mask, span, classes }
+    }
+}
+
+impl MasksVec {
+    /// returns a mask with the bit of the last byte of each tag set if all
+    /// the bits of the tag are set in `eq`
+    ///
+    /// adding `low_mask` to a tag with all its bits set except the last one
+    /// carries up to the last one
+    #[inline(always)]
+    fn ends(&self, eq: u32) -> u32 {
+        let tmp_mask = eq & !self.high_mask;
+        let (tmp_mask2, _) = tmp_mask.overflowing_add(self.low_mask);
+        let tmp_mask3 = tmp_mask2 & eq;
+        tmp_mask3 & self.high_mask
+    }
+
+    /// returns the ends of the tags matched by the first `len` bytes of the
+    /// input, and the ends of the longer tags starting with them
+    #[inline(always)]
+    fn candidates(&self, eq: u32, len: usize) -> (u32, u32) {
+        if len >= 16 {
+            return (self.ends(eq), 0);
+        }
+
+        // positions past the end of the input
+        let mut unknown = 0u32;
+        for (j, &index) in dump(self.shuf_mask).iter().enumerate() {
+            if index as usize >= len {
+                unknown |= 1 << j;
+            }
+        }
+
+        let full = self.ends(eq & !unknown);
+        let partial = self.ends(eq | unknown) & !full;
+        (full, partial)
+    }
+
+    /// id of the tag at the start of `input`, or 0xFF
+    ///
+    /// like [`MultiTag`] with [`MatchPolicy::FirstDeclared`] in complete
+    /// mode, the first declared of the matching tags is returned
+    pub fn classify(&self, input: &[u8]) -> u8 {
+        let mut ids = [0xFFu8];
+        self.classify_slices(std::iter::once(input), &mut ids);
+        ids[0]
+    }
+
+    /// writes in `ids[i]` the id of the tag at `input[offsets[i]..]`, see
+    /// [`MasksVec::classify`]
+    ///
+    /// panics if an offset is past the end of `input`, or if `ids` is shorter
+    /// than `offsets`
+    pub fn classify_offsets(&self, input: &[u8], offsets: &[usize], ids: &mut [u8]) {
+        assert!(ids.len() >= offsets.len(), "there must be one id per input");
+        self.classify_slices(offsets.iter().map(|&offset| &input[offset..]), ids);
+    }
+
+    /// writes the id of the tag at the start of each slice in `ids`, and
+    /// returns the number of slices, see [`MasksVec::classify`]
+    ///
+    /// with AVX2, the slices are compared 4 at a time, so the latencies of
+    /// their vector operations overlap
+    ///
+    /// panics if `ids` is shorter than the number of slices
+    pub fn classify_slices<'a, I: IntoIterator<Item = &'a [u8]>>(&self, slices: I, ids: &mut [u8]) -> usize {
+        let mut slices = slices.into_iter();
+        let mut count = 0usize;
+        loop {
+            let mut block: [&[u8]; 4] = [&[]; 4];
+            let mut n = 0usize;
+            while n < 4 {
+                match slices.next() {
+                    Some(slice) => block[n] = slice,
+                    None => break,
+                }
+                n += 1;
+            }
+            if n == 0 {
+                return count;
+            }
+            assert!(ids.len() >= count + n, "there must be one id per input");
+
+            // short inputs are copied to zero padded buffers, like in `MultiTag::parse`
+            let mut bufs = [[0u8; 16]; 4];
+            for (buf, slice) in bufs.iter_mut().zip(block.iter()) {
+                if slice.len() < 16 {
+                    buf[..slice.len()].copy_from_slice(slice);
+                }
+            }
+            let mut data: [&[u8]; 4] = [&[]; 4];
+            for (j, data) in data.iter_mut().enumerate() {
+                *data = if block[j].len() < 16 { &bufs[j][..] } else { block[j] };
+            }
+
+            let eqs = self.eq_masks(data, n);
+
+            for j in 0..n {
+                let (full, _) = self.candidates(eqs[j], block[j].len());
+                ids[count + j] = if full == 0 { 0xFF } else { self.ids[full.trailing_zeros() as usize] };
+            }
+            count += n;
+        }
+    }
+
+    /// `eq_mask` over the first `n` of `inputs`, 4 at a time with AVX2
+    #[inline(always)]
+    fn eq_masks(&self, inputs: [&[u8]; 4], n: usize) -> [u32; 4] {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if n == 4 && crate::cpu::has_avx2() {
+                return unsafe { eq_masks4_avx2(self, inputs) };
+            }
+        }
+
+        let mut eqs = [0u32; 4];
+        for j in 0..n {
+            eqs[j] = eq_mask(self, inputs[j]);
+        }
+        eqs
+    }
+}
+
+/// `eq_mask_avx2` over 4 inputs: the 4 dependency chains are independent,
+/// so the CPU can run them in parallel
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn eq_masks4_avx2(m: &MasksVec, inputs: [&[u8]; 4]) -> [u32; 4] {
+    let v0 = load16(inputs[0]);
+    let v1 = load16(inputs[1]);
+    let v2 = load16(inputs[2]);
+    let v3 = load16(inputs[3]);
+
+    [
+        eq_mask_vector_avx2(m, v0),
+        eq_mask_vector_avx2(m, v1),
+        eq_mask_vector_avx2(m, v2),
+        eq_mask_vector_avx2(m, v3),
+    ]
+}
+
+/// `process` step by step, with the intermediate values printed, or `None`
+/// if the CPU does not support AVX2
+#[cfg(all(test, target_arch = "x86_64"))]
+fn avx(i: &[u8]) -> Option<u8> {
+    use nom::HexDisplay;
+
+    if !crate::cpu::has_avx2() {
+        return None;
+    }
+
+    println!("input:\n{}", i.to_hex(16));
+    let input = unsafe { load16(i) };
+    let d = dump(input);
+    println!("dumped:\n{}", &d.to_hex(16));
+
+    let strings = [&b"Acce"[..], &b"ConA"[..], &b"Date"[..], &b"Cont"[..], &b"Forw"[..], &b"Host"[..], &b"User"[..], &b"Upgr"[..]];
+    let Masks { cmp, shuf_mask, high_mask, low_mask, ids, .. } = prepare(&strings[..]);
+    let cmp_mask = vector(cmp);
+    print_hex("cmp_mask", cmp_mask);
+    let shuf_mask = vector(shuf_mask);
+    let shuffled = unsafe { _mm256_shuffle_epi8(input, shuf_mask) };
+    print_hex("shuffled", shuffled);
+
+    let cmpres = unsafe { _mm256_cmpeq_epi8(shuffled, cmp_mask) };
+    print_hex("cmpres", cmpres);
+
+    let maskres = unsafe { _mm256_movemask_epi8(cmpres) };
+    println!("maskres: LE {:x?}, BE: {:x?}", maskres.to_le_bytes(), maskres.to_be_bytes());
+    print_u32("maskres_u32", maskres as u32);
+    print_u32("hi", high_mask);
+    print_u32("low", low_mask);
+    let tmp_mask = maskres as u32 & !high_mask;
+    print_u32("mask & !hi", tmp_mask);
+    let (tmp_mask2, _) = tmp_mask.overflowing_add(low_mask);
+    print_u32("(mask & !hi) + low", tmp_mask2);
+    let tmp_mask3 = tmp_mask2 & maskres as u32;
+    print_u32("((mask & !hi) + low) & mask", tmp_mask3);
+    let res = tmp_mask3 & high_mask;
+    print_u32("((mask & !hi) + low) & mask & hi", res);
+
+    let position = res.trailing_zeros();
+    println!("first end: {}", position);
+
+    let idx = ids[position as usize];
+    println!("found? {:?}", std::str::from_utf8(strings[idx as usize]).unwrap());
+    Some(idx)
+}
+
+/// 32 bytes of masks, kept in a vector on x86_64
+#[cfg(target_arch = "x86_64")]
+type Vector = __m256i;
+#[cfg(not(target_arch = "x86_64"))]
+type Vector = [u8; 32];
+
+// plain memory copies, so those do not need AVX
+#[cfg(target_arch = "x86_64")]
+const fn vector(bytes: [u8; 32]) -> Vector {
+    unsafe { std::mem::transmute::<[u8; 32], __m256i>(bytes) }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn dump(i: Vector) -> [u8; 32] {
+    unsafe { std::mem::transmute::<__m256i, [u8; 32]>(i) }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+const fn vector(bytes: [u8; 32]) -> Vector {
+    bytes
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn dump(i: Vector) -> [u8; 32] {
+    i
+}
+
+/// the low and high 128 bits lanes of `i`
+#[cfg(target_arch = "x86_64")]
+fn halves(i: __m256i) -> [__m128i; 2] {
+    unsafe { std::mem::transmute::<__m256i, [__m128i; 2]>(i) }
+}
+
+/// loads the first 16 bytes of `i` in both lanes of a 256 bits vector
+///
+/// # Safety
+///
+/// the CPU must support AVX, and `i` must contain at least 16 bytes
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx")]
+pub unsafe fn load16(i: &[u8]) -> __m256i {
+    _mm256_loadu2_m128i(
+        i.as_ptr() as *const _,
+        i.as_ptr() as *const _)
+}
+
+#[cfg(all(test, target_arch = "x86_64"))]
+fn print_hex(prefix: &str, i: __m256i) {
+    use nom::HexDisplay;
+
+    println!("{}:\n{}", prefix, &(dump(i)).to_hex(16));
+}
+
+#[cfg(all(test, target_arch = "x86_64"))]
+fn print_u32(suffix: &str, i: u32) {
+    let d = i.to_le_bytes();
+    println!("u32 bytes:{:x?}", d);
+    let mut s = String::new();
+    for idx in 0..32 {
+        s.push(if ((i >> idx) & 1) == 1 { '1' } else { '_' });
+    }
+    println!("{}\t{}", s, suffix);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn avx_test() {
+        if let Some(idx) = avx(&b"Content-Length: 1234\r\nHost: hello.com"[..]) {
+            assert_eq!(idx, 3);
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn process_order_test() {
+        if !crate::cpu::has_avx2() {
+            return;
+        }
+
+        let input = &b"Content-Length: 1234\r\n"[..];
+        let first = MasksVec::from(prepare(&[&b"Cont"[..], &b"Content"[..]]));
+        let last = MasksVec::from(prepare(&[&b"Content"[..], &b"Cont"[..]]));
+        let none = MasksVec::from(prepare(&[&b"Host"[..]]));
+
+        unsafe {
+            assert_eq!(process(&first, load16(input)), first.classify(input));
+            assert_eq!(process(&first, load16(input)), 0);
+            assert_eq!(process(&last, load16(input)), 0);
+            assert_eq!(process(&none, load16(input)), 0xFF);
+        }
+    }
+
+    #[test]
+    fn prepare_test() {
+        let strings = [&b"Acce"[..], &b"Cont"[..], &b"Date"[..], &b"ConA"[..], &b"Forw"[..], &b"Host"[..], &b"User"[..], &b"Up34"[..]];
+        let Masks { cmp, shuf_mask, high_mask, low_mask, ids, .. } = prepare(&strings[..]);
+
+        assert_eq!(&cmp[..], &b"AcceContDateConAForwHostUserUp34"[..]);
+        assert_eq!(&shuf_mask[..4], &[0, 1, 2, 3][..]);
+        assert_eq!(&shuf_mask[28..], &[0, 1, 2, 3][..]);
+        assert_eq!(high_mask, 0x8888_8888);
+        assert_eq!(low_mask, 0x1111_1111);
+        assert_eq!(ids[3], 0);
+        assert_eq!(ids[31], 7);
+        assert_eq!(ids[0], 0xFF);
+    }
+
+    #[test]
+    fn scalar_fallback_test() {
+        let tags = [&b"Acce"[..], &b"Cont"[..], &b"Date"[..], &b"Host"[..], &b"X-Forwarded-For"[..]];
+        let masks = MasksVec::from(prepare(&tags[..]));
+
+        let inputs = [
+            &b"Accept: */*\r\n\r\n"[..],
+            &b"Content-Length: 12"[..],
+            &b"Host: hello.com\r\n"[..],
+            &b"X-Forwarded-For: 1.2.3.4"[..],
+            &b"Server: hello.com\r\n"[..],
+            &b"\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0"[..],
+        ];
+        // bit of the last byte of the matched tag
+        let expected = [1u32 << 3, 1 << 7, 1 << 15, 1 << 30, 0, 0];
+
+        for (input, expected) in inputs.iter().zip(expected.iter()) {
+            assert_eq!(masks.ends(eq_mask_scalar(&masks, input)), *expected);
+            #[cfg(target_arch = "x86_64")]
+            if crate::cpu::has_avx2() {
+                assert_eq!(masks.ends(unsafe { eq_mask_avx2(&masks, input) }), *expected);
+            }
+            #[cfg(target_arch = "x86_64")]
+            if crate::cpu::has_ssse3() {
+                assert_eq!(masks.ends(unsafe { eq_mask_ssse3(&masks, input) }), *expected);
+            }
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn backends_test() {
+        // tags crossing the middle of the 32 bytes groups
+        let tags: &[&[u8]] = &[
+            b"Accept-Charset", b"Accept-Encoding", b"Accept", b"Authorization",
+            b"Content-Encoding", b"Content-Length", b"Date", b"Expect",
+            b"Forwarded", b"Host", b"If-Modified-Sinc", b"X-Forwarded-For",
+        ];
+
+        for &no_case in &[false, true] {
+            let mut start = 0;
+            while start < tags.len() {
+                let end = group_end(Tags::Bytes(tags), start);
+                let masks = MasksVec::from(prepare_group(Tags::Bytes(tags), start, end, no_case));
+                start = end;
+
+                // every tag, their prefixes and case variations, and bytes
+                // around the ASCII letters
+                let mut inputs: Vec<Vec<u8>> = Vec::new();
+                for tag in tags {
+                    for len in 0..=tag.len() {
+                        let mut input = tag[..len].to_vec();
+                        input.resize(16, b'@');
+                        inputs.push(input.clone());
+                        inputs.push(input.to_ascii_uppercase());
+                        inputs.push(input.iter().map(|b| b ^ 0x80).collect());
+                    }
+                }
+                for b in 0..=255u8 {
+                    inputs.push(vec![b; 16]);
+                }
+
+                for input in &inputs {
+                    let expected = eq_mask_scalar(&masks, input);
+                    #[cfg(target_arch = "x86_64")]
+                    if crate::cpu::has_avx2() {
+                        assert_eq!(unsafe { eq_mask_avx2(&masks, input) }, expected, "{:?}", input);
+                    }
+                    #[cfg(target_arch = "x86_64")]
+                    if crate::cpu::has_ssse3() {
+                        assert_eq!(unsafe { eq_mask_ssse3(&masks, input) }, expected, "{:?}", input);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn multitag_groups_test() {
+        use nom::error::Error;
+        use crate::mode::Complete;
+
+        const TAGS: &[&[u8]] = &[
+            b"Accept-Charset", b"Accept-Encoding", b"Accept", b"Authorization",
+            b"Content-Encoding", b"Content-Length", b"Date", b"Expect",
+            b"Forwarded", b"Host", b"If-Modified-Sinc", b"Referer",
+            b"User-Agent", b"Upgrade", b"Via", b"X-Forwarded-For",
+        ];
+        static GROUPS: [MasksVec; group_count(TAGS)] = prepare_groups(TAGS);
+        static HEADERS: MultiTag<Complete> = MultiTag::from_groups(&GROUPS);
+        assert_eq!(GROUPS.len(), 7);
+
+        let parser = complete::multitag(TAGS);
+        assert_eq!(parser.groups.len(), 7);
+
+        let mut inputs: Vec<Vec<u8>> = TAGS.iter().map(|tag| [tag, &b": 1234567890abcdef"[..]].concat()).collect();
+        inputs.push(b"Server: hello.com\r\n".to_vec());
+        inputs.push(b"Accep".to_vec());
+
+        for input in inputs.iter() {
+            // the first declared tag matching the input wins
+            let expected = TAGS.iter().position(|tag| input.starts_with(tag));
+            let expected = expected.map(|idx| (&input[TAGS[idx].len()..], (idx, TAGS[idx])));
+            let res = parser.parse::<Error<_>>(input).ok();
+            assert_eq!(res, expected, "input: {:?}", std::str::from_utf8(input));
+            assert_eq!(HEADERS.parse::<Error<_>>(input).ok(), expected);
+        }
+    }
+
+    #[test]
+    fn multitag_policy_test() {
+        use nom::bytes::{complete::tag, streaming};
+        use nom::branch::alt;
+        use nom::combinator::map;
+        use nom::error::Error;
+        use crate::mode::{Complete, Streaming};
+
+        let tags = [
+            &b"Accept"[..], &b"Accept-Charset"[..], &b"Accept-Encoding"[..], &b"Acc"[..],
+            &b"Content-Type"[..], &b"Content-Length"[..], &b"Content"[..], &b"Via"[..], &b"V"[..],
+        ];
+        let first = MultiTagBuilder::new(&tags[..]).build::<Complete>().unwrap();
+        let longest = MultiTagBuilder::new(&tags[..]).policy(MatchPolicy::Longest).build::<Complete>().unwrap();
+        assert!(first.groups.len() > 1);
+        let streaming_first = MultiTagBuilder::new(&tags[..]).build::<Streaming>().unwrap();
+        let streaming_longest = MultiTagBuilder::new(&tags[..]).policy(MatchPolicy::Longest).build::<Streaming>().unwrap();
+
+        type Res<'a> = IResult<&'a [u8], (usize, &'a [u8]), Error<&'a [u8]>>;
+        let streaming_first_reference = |i| -> Res {
+            alt((
+                map(streaming::tag(tags[0]), |t| (0, t)),
+                map(streaming::tag(tags[1]), |t| (1, t)),
+                map(streaming::tag(tags[2]), |t| (2, t)),
+                map(streaming::tag(tags[3]), |t| (3, t)),
+                map(streaming::tag(tags[4]), |t| (4, t)),
+                map(streaming::tag(tags[5]), |t| (5, t)),
+                map(streaming::tag(tags[6]), |t| (6, t)),
+                map(streaming::tag(tags[7]), |t| (7, t)),
+                map(streaming::tag(tags[8]), |t| (8, t)),
+            ))(i)
+        };
+        let streaming_longest_reference = |i| -> Res {
+            alt((
+                map(streaming::tag(tags[2]), |t| (2, t)),
+                map(streaming::tag(tags[1]), |t| (1, t)),
+                map(streaming::tag(tags[5]), |t| (5, t)),
+                map(streaming::tag(tags[4]), |t| (4, t)),
+                map(streaming::tag(tags[6]), |t| (6, t)),
+                map(streaming::tag(tags[0]), |t| (0, t)),
+                map(streaming::tag(tags[3]), |t| (3, t)),
+                map(streaming::tag(tags[7]), |t| (7, t)),
+                map(streaming::tag(tags[8]), |t| (8, t)),
+            ))(i)
+        };
+        let first_reference = |i| -> Res {
+            alt((
+                map(tag(tags[0]), |t| (0, t)),
+                map(tag(tags[1]), |t| (1, t)),
+                map(tag(tags[2]), |t| (2, t)),
+                map(tag(tags[3]), |t| (3, t)),
+                map(tag(tags[4]), |t| (4, t)),
+                map(tag(tags[5]), |t| (5, t)),
+                map(tag(tags[6]), |t| (6, t)),
+                map(tag(tags[7]), |t| (7, t)),
+                map(tag(tags[8]), |t| (8, t)),
+            ))(i)
+        };
+        // the same tags, sorted by decreasing length
+        let longest_reference = |i| -> Res {
+            alt((
+                map(tag(tags[2]), |t| (2, t)),
+                map(tag(tags[1]), |t| (1, t)),
+                map(tag(tags[5]), |t| (5, t)),
+                map(tag(tags[4]), |t| (4, t)),
+                map(tag(tags[6]), |t| (6, t)),
+                map(tag(tags[0]), |t| (0, t)),
+                map(tag(tags[3]), |t| (3, t)),
+                map(tag(tags[7]), |t| (7, t)),
+                map(tag(tags[8]), |t| (8, t)),
+            ))(i)
+        };
+
+        for input in [
+            &b"Accept-Charset: utf-8\r\n"[..],
+            &b"Accept-Encoding: gzip\r\n"[..],
+            &b"Accept: */*\r\n\r\n"[..],
+            &b"Content-Length: 1234\r\n"[..],
+            &b"Content-Type: text/html"[..],
+            &b"Content-Encoding: gzip"[..],
+            &b"Via: 1.1 proxy\r\n\r\n"[..],
+            &b"Vary: Accept\r\n\r\n"[..],
+            &b"Host: hello.com\r\n"[..],
+        ].iter() {
+            for len in 0..=input.len() {
+                let input = &input[..len];
+                assert_eq!(first.parse(input), first_reference(input), "input: {:?}", std::str::from_utf8(input));
+                assert_eq!(longest.parse(input), longest_reference(input), "input: {:?}", std::str::from_utf8(input));
+                assert_eq!(streaming_first.parse(input), streaming_first_reference(input), "input: {:?}", std::str::from_utf8(input));
+                assert_eq!(streaming_longest.parse(input), streaming_longest_reference(input), "input: {:?}", std::str::from_utf8(input));
+
+                let buf = crate::padded::PaddedBuf::from(input);
+                let padded = streaming_longest.parse_padded::<Error<_>>(buf.input());
+                let expected = streaming_longest_reference(input);
+                assert_eq!(padded.is_ok(), expected.is_ok());
+                if let (Ok((rest, output)), Ok((expected_rest, expected_output))) = (padded, expected) {
+                    assert_eq!((rest.as_bytes(), output), (expected_rest, expected_output));
+                }
+            }
+        }
+
+        const TAGS: &[&[u8]] = &[b"Acc", b"Accept"];
+        static GROUPS: [MasksVec; group_count(TAGS)] = prepare_groups(TAGS);
+        static LONGEST: MultiTag<Complete> = MultiTag::from_groups(&GROUPS).with_policy(MatchPolicy::Longest);
+        assert_eq!(LONGEST.parse::<Error<_>>(&b"Accept: */*"[..]), Ok((&b": */*"[..], (1, &b"Accept"[..]))));
+        assert_eq!(
+            MultiTag::<Complete>::from_groups(&GROUPS).parse::<Error<_>>(&b"Accept: */*"[..]),
+            Ok((&b"ept: */*"[..], (0, &b"Acc"[..])))
+        );
+    }
+
+    #[test]
+    fn multitag_no_case_test() {
+        use nom::bytes::complete::tag_no_case;
+        use nom::branch::alt;
+        use nom::combinator::map;
+        use nom::error::Error;
+        use crate::mode::Complete;
+
+        const NO_CASE_TAGS: [&[u8]; 4] = [b"Content-Length", b"Host", b"X-Forwarded-For", b"via@["];
+        let tags = NO_CASE_TAGS;
+        let parser = MultiTagBuilder::new(&tags[..]).no_case(true).build::<Complete>().unwrap();
+        type Res<'a> = IResult<&'a [u8], (usize, &'a [u8]), Error<&'a [u8]>>;
+        fn reference(i: &[u8]) -> Res<'_> {
+            alt((
+                map(tag_no_case(NO_CASE_TAGS[0]), |t| (0, t)),
+                map(tag_no_case(NO_CASE_TAGS[1]), |t| (1, t)),
+                map(tag_no_case(NO_CASE_TAGS[2]), |t| (2, t)),
+                map(tag_no_case(NO_CASE_TAGS[3]), |t| (3, t)),
+            ))(i)
+        }
+
+        for tag in tags.iter() {
+            let mut inputs = vec![
+                [tag, &b": 1234567890abcdef"[..]].concat(),
+                [&tag.to_ascii_lowercase()[..], &b": 1234567890abcdef"[..]].concat(),
+                [&tag.to_ascii_uppercase()[..], &b": 1234567890abcdef"[..]].concat(),
+            ];
+            // every byte value at every position of the tag
+            for position in 0..tag.len() {
+                for c in 0..=255u8 {
+                    let mut input = [&tag.to_ascii_uppercase()[..], &b": 1234567890abcdef"[..]].concat();
+                    input[position] = c;
+                    inputs.push(input);
+                }
+            }
+
+            for input in inputs.iter() {
+                assert_eq!(parser.parse(input), reference(input), "input: {:?}", input);
+                #[cfg(target_arch = "x86_64")]
+                for masks in parser.groups.iter() {
+                    if crate::cpu::has_avx2() {
+                        assert_eq!(unsafe { eq_mask_avx2(masks, input) }, eq_mask_scalar(masks, input));
+                    }
+                }
+            }
+        }
+
+        const TAGS: &[&[u8]] = &[b"Host", b"Content-Length"];
+        static GROUPS: [MasksVec; group_count(TAGS)] = prepare_groups_no_case(TAGS);
+        static HEADERS: MultiTag<Complete> = MultiTag::from_groups(&GROUPS);
+        assert_eq!(
+            HEADERS.parse::<Error<_>>(&b"content-length: 12"[..]),
+            Ok((&b": 12"[..], (1, &b"content-length"[..])))
+        );
+        let exact = complete::multitag(TAGS);
+        assert_eq!(
+            exact.parse::<Error<_>>(&b"content-length: 12"[..]),
+            Err(Err::Error(Error::new(&b"content-length: 12"[..], ErrorKind::Tag)))
+        );
+    }
+
+    #[test]
+    fn multitag_values_test() {
+        use nom::error::Error;
+        use crate::mode::{Complete, Streaming};
+
+        multitag_enum! {
+            /// a few HTTP header names
+            enum HeaderName {
+                Accept = b"Accept",
+                AcceptCharset = b"Accept-Charset",
+                ContentLength = b"Content-Length",
+                ContentType = b"Content-Type",
+                Host = b"Host",
+                XForwardedFor = b"X-Forwarded-For",
+            }
+        }
+        assert_eq!(HeaderName::TAGS.len(), 6);
+        assert_eq!(HeaderName::ContentType.tag(), b"Content-Type");
+
+        let parser = MultiTagBuilder::new(HeaderName::TAGS)
+            .policy(MatchPolicy::Longest)
+            .no_case(true)
+            .build_values::<Complete, _>(HeaderName::VALUES)
+            .unwrap();
+        for value in HeaderName::VALUES.iter() {
+            let input = [value.tag(), &b": 1234"[..]].concat();
+            assert_eq!(parser.parse::<Error<_>>(&input), Ok((&b": 1234"[..], *value)));
+            let input = input.to_ascii_lowercase();
+            assert_eq!(parser.parse::<Error<_>>(&input), Ok((&b": 1234"[..], *value)));
+        }
+        assert_eq!(
+            parser.parse::<Error<_>>(&b"Server: hello.com\r\n"[..]),
+            Err(Err::Error(Error::new(&b"Server: hello.com\r\n"[..], ErrorKind::Tag)))
+        );
+
+        let streaming = streaming::multitag_values(&[(&b"GET"[..], 'g'), (&b"POST"[..], 'p')]);
+        assert_eq!(streaming.parse::<Error<_>>(&b"POST /"[..]), Ok((&b" /"[..], 'p')));
+        assert_eq!(streaming.parse::<Error<_>>(&b"PO"[..]), Err(Err::Incomplete(Needed::new(2))));
+
+        static GROUPS: [MasksVec; group_count(HeaderName::TAGS)] = prepare_groups(HeaderName::TAGS);
+        static HEADERS: MultiTagValues<HeaderName, Streaming> =
+            MultiTagValues::from_groups(&GROUPS, HeaderName::VALUES).with_policy(MatchPolicy::Longest);
+        let mut parser = nom::sequence::terminated(HEADERS.clone(), nom::bytes::streaming::tag(": "));
+        let res: IResult<_, _, Error<&[u8]>> = parser(&b"Accept-Charset: utf-8\r\n"[..]);
+        assert_eq!(res, Ok((&b"utf-8\r\n"[..], HeaderName::AcceptCharset)));
+    }
+
+    #[test]
+    fn byte_class_test() {
+        let members = |class: ByteClass| (0..=255u8).filter(|&b| class.matches(b)).collect::<Vec<u8>>();
+
+        assert_eq!(members(ByteClass::byte(b'x')), b"x");
+        assert_eq!(members(ByteClass::ANY).len(), 256);
+        assert_eq!(members(ByteClass::DIGIT), b"0123456789");
+        assert_eq!(members(ByteClass::range(0xF0, 0xFF)).len(), 16);
+        assert_eq!(members(ByteClass::either(b'1', b'0')), b"01");
+        assert_eq!(members(ByteClass::either(b'x', b'X')), b"Xx");
+        assert_eq!(members(ByteClass::masked(b'A', 0xF0)), b"@ABCDEFGHIJKLMNO");
+        assert_eq!(ByteClass::literal(b"GET"), [ByteClass::byte(b'G'), ByteClass::byte(b'E'), ByteClass::byte(b'T')]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the bytes must be consecutive or differ by one bit")]
+    fn byte_class_either_test() {
+        ByteClass::either(b'a', b'z');
+    }
+
+    #[test]
+    fn multitag_patterns_test() {
+        use nom::error::{Error, ErrorKind};
+        use crate::mode::{Complete, Streaming};
+
+        const HTTP: [ByteClass; 8] = {
+            let mut p = ByteClass::literal(b"HTTP/1.x");
+            p[7] = ByteClass::either(b'0', b'1');
+            p
+        };
+        const RTSP: [ByteClass; 8] = ByteClass::literal(b"RTSP/1.0");
+        const SUCCESS: [ByteClass; 4] = [ByteClass::byte(b'2'), ByteClass::DIGIT, ByteClass::DIGIT, ByteClass::byte(b' ')];
+        const ANY_ERROR: [ByteClass; 4] = [ByteClass::range(b'4', b'5'), ByteClass::ANY, ByteClass::ANY, ByteClass::byte(b' ')];
+        const PATTERNS: &[&[ByteClass]] = &[&HTTP, &RTSP, &SUCCESS, &ANY_ERROR];
+
+        static GROUPS: [MasksVec; pattern_group_count(PATTERNS)] = prepare_pattern_groups(PATTERNS);
+        static STATUS: MultiTag<Complete> = MultiTag::from_groups(&GROUPS);
+
+        let complete = MultiTagBuilder::patterns(PATTERNS).build::<Complete>().unwrap();
+        let streaming = MultiTagBuilder::patterns(PATTERNS).build::<Streaming>().unwrap();
+        for parser in [&complete, &STATUS] {
+            assert_eq!(parser.parse::<Error<_>>(&b"HTTP/1.1 200 OK"[..]), Ok((&b" 200 OK"[..], (0, &b"HTTP/1.1"[..]))));
+            assert_eq!(parser.parse::<Error<_>>(&b"HTTP/1.0 200 OK"[..]), Ok((&b" 200 OK"[..], (0, &b"HTTP/1.0"[..]))));
+            assert_eq!(parser.parse::<Error<_>>(&b"RTSP/1.0 200 OK"[..]), Ok((&b" 200 OK"[..], (1, &b"RTSP/1.0"[..]))));
+            assert_eq!(parser.parse::<Error<_>>(&b"204 No Content"[..]), Ok((&b"No Content"[..], (2, &b"204 "[..]))));
+            assert_eq!(parser.parse::<Error<_>>(&b"4\xFF! x"[..]), Ok((&b"x"[..], (3, &b"4\xFF! "[..]))));
+            assert_eq!(
+                parser.parse::<Error<_>>(&b"HTTP/1.2 200 OK"[..]),
+                Err(Err::Error(Error::new(&b"HTTP/1.2 200 OK"[..], ErrorKind::Tag)))
+            );
+            assert_eq!(parser.parse::<Error<_>>(&b"2x0 "[..]), Err(Err::Error(Error::new(&b"2x0 "[..], ErrorKind::Tag))));
+            assert_eq!(parser.parse::<Error<_>>(&b"304 "[..]), Err(Err::Error(Error::new(&b"304 "[..], ErrorKind::Tag))));
+        }
+
+        assert_eq!(streaming.parse::<Error<_>>(&b"HTTP/1"[..]), Err(Err::Incomplete(Needed::new(2))));
+        assert_eq!(streaming.parse::<Error<_>>(&b"20"[..]), Err(Err::Incomplete(Needed::new(2))));
+        assert_eq!(streaming.parse::<Error<_>>(&b"2x"[..]), Err(Err::Error(Error::new(&b"2x"[..], ErrorKind::Tag))));
+
+        // same tags as patterns of single bytes
+        assert_eq!(
+            MultiTagBuilder::patterns(&[&RTSP, &ByteClass::literal(b"RTSP/1.0")]).build::<Complete>().err(),
+            Some(BuildError::DuplicateTag { first: 0, second: 1 })
+        );
+        assert_eq!(
+            MultiTagBuilder::patterns(&[&RTSP, &ByteClass::literal(b"rtsp/1.0")]).no_case(true).build::<Complete>().err(),
+            Some(BuildError::DuplicateTag { first: 0, second: 1 })
+        );
+
+        // the backends give the same masks with classes
+        #[cfg(target_arch = "x86_64")]
+        {
+            let tags = Tags::Patterns(PATTERNS);
+            let masks = MasksVec::from(prepare_group(tags, 0, group_end(tags, 0), false));
+            for b in 0..=255u8 {
+                for position in 0..16 {
+                    let mut input = *b"HTTP/1.1 200 OK ";
+                    input[position] = b;
+                    let expected = eq_mask_scalar(&masks, &input);
+                    if crate::cpu::has_avx2() {
+                        assert_eq!(unsafe { eq_mask_avx2(&masks, &input) }, expected);
+                    }
+                    if crate::cpu::has_ssse3() {
+                        assert_eq!(unsafe { eq_mask_ssse3(&masks, &input) }, expected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn multitag_delimiter_test() {
+        use nom::error::{Error, ErrorKind};
+        use crate::mode::{Complete, Streaming};
+
+        const fn is_delimiter(c: u8) -> u8 {
+            (c == b':' || c == b' ') as u8
+        }
+        const DELIMITERS: [u8; 256] = crate::make_lookup_table!(is_delimiter);
+        const TAGS: &[&[u8]] = &[b"Host", b"Hostname", b"Via"];
+
+        static GROUPS: [MasksVec; group_count(TAGS)] = prepare_groups(TAGS);
+        static HEADERS: MultiTag<Streaming> = MultiTag::from_groups(&GROUPS).with_delimiters(&DELIMITERS);
+
+        let streaming = MultiTagBuilder::new(TAGS).delimiters(&DELIMITERS).build::<Streaming>().unwrap();
+        let complete = MultiTagBuilder::new(TAGS).delimiters(&DELIMITERS).build::<Complete>().unwrap();
+        let longest = MultiTagBuilder::new(TAGS)
+            .delimiters(&DELIMITERS)
+            .policy(MatchPolicy::Longest)
+            .build::<Streaming>()
+            .unwrap();
+
+        for parser in [&streaming, &longest, &HEADERS] {
+            assert_eq!(parser.parse::<Error<_>>(&b"Host: x"[..]), Ok((&b": x"[..], (0, &b"Host"[..]))));
+            assert_eq!(parser.parse::<Error<_>>(&b"Hostname: x"[..]), Ok((&b": x"[..], (1, &b"Hostname"[..]))));
+            assert_eq!(parser.parse::<Error<_>>(&b"Via 1.1"[..]), Ok((&b" 1.1"[..], (2, &b"Via"[..]))));
+            assert_eq!(parser.parse::<Error<_>>(&b"Hostnames: x"[..]), Err(Err::Error(Error::new(&b"Hostnames: x"[..], ErrorKind::Tag))));
+            assert_eq!(parser.parse::<Error<_>>(&b"Hosts: x"[..]), Err(Err::Error(Error::new(&b"Hosts: x"[..], ErrorKind::Tag))));
+
+            // the delimiter is not available yet
+            assert_eq!(parser.parse::<Error<_>>(&b"Via"[..]), Err(Err::Incomplete(Needed::new(1))));
+            assert_eq!(parser.parse::<Error<_>>(&b"Hostn"[..]), Err(Err::Incomplete(Needed::new(4))));
+        }
+        assert_eq!(streaming.parse::<Error<_>>(&b"Host"[..]), Err(Err::Incomplete(Needed::new(1))));
+        // the longest tag may be `Hostname`
+        assert_eq!(longest.parse::<Error<_>>(&b"Host"[..]), Err(Err::Incomplete(Needed::new(5))));
+
+        // the end of the input ends the tag
+        assert_eq!(complete.parse::<Error<_>>(&b"Host"[..]), Ok((&b""[..], (0, &b"Host"[..]))));
+        assert_eq!(complete.parse::<Error<_>>(&b"Hostname"[..]), Ok((&b""[..], (1, &b"Hostname"[..]))));
+        assert_eq!(complete.parse::<Error<_>>(&b"Hostn"[..]), Err(Err::Error(Error::new(&b"Hostn"[..], ErrorKind::Tag))));
+
+        let values = MultiTagValues::<_, Complete>::from_groups(&GROUPS, &['h', 'n', 'v']).with_delimiters(&DELIMITERS);
+        assert_eq!(values.parse::<Error<_>>(&b"Hostname x"[..]), Ok((&b" x"[..], 'n')));
+
+        let buf = crate::padded::PaddedBuf::from(&b"Hostname:"[..]);
+        assert_eq!(
+            streaming.parse_padded::<Error<_>>(buf.input()).map(|(rest, (idx, _))| (rest.as_bytes(), idx)),
+            Ok((&b":"[..], 1))
+        );
+    }
+
+    #[test]
+    fn classify_test() {
+        use nom::error::Error;
+        use crate::mode::Complete;
+
+        const TAGS: &[&[u8]] = &[b"Host", b"Accept", b"Content", b"Date", b"\r\n"];
+        static GROUPS: [MasksVec; group_count(TAGS)] = prepare_groups(TAGS);
+        static PARSER: MultiTag<Complete> = MultiTag::from_groups(&GROUPS);
+        let masks = &GROUPS[0];
+
+        let input = &b"GET / HTTP/1.1\r\nHost: a\r\nAccept: */*\r\nDate: today\r\nContent-Length: 3\r\n\r\nabc"[..];
+        let offsets: Vec<usize> = (0..=input.len()).collect();
+        let mut ids = vec![0u8; offsets.len()];
+        masks.classify_offsets(input, &offsets, &mut ids);
+
+        for (&offset, &id) in offsets.iter().zip(ids.iter()) {
+            let expected = match PARSER.parse::<Error<_>>(&input[offset..]) {
+                Ok((_, (idx, _))) => idx as u8,
+                Err(_) => 0xFF,
+            };
+            assert_eq!(id, expected, "offset {}", offset);
+            assert_eq!(masks.classify(&input[offset..]), expected);
+        }
+        assert_eq!(ids.iter().filter(|&&id| id != 0xFF).count(), 10);
+
+        let lines: Vec<&[u8]> = input.split(|&c| c == b'\n').collect();
+        let mut ids = [0u8; 16];
+        assert_eq!(masks.classify_slices(lines.iter().copied(), &mut ids), 7);
+        // the last `\r` is not followed by `\n` anymore
+        assert_eq!(ids[..7], [0xFF, 0, 1, 3, 2, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    #[should_panic(expected = "there must be one id per input")]
+    fn classify_ids_test() {
+        let masks = MasksVec::from(prepare(&[b"Host"]));
+        masks.classify_offsets(b"Host: a", &[0, 1], &mut [0u8; 1]);
+    }
+
+    #[test]
+    fn take_until_any_test() {
+        use nom::error::{Error, ErrorKind};
+
+        let tags: &[&[u8]] = &[b"ERROR", b"WARN", b"panicked at", b"\r\n\r\n", b"WARNING"];
+        let streaming = streaming::take_until_any(tags);
+        let complete = complete::take_until_any(tags);
+
+        let log = &b"INFO starting\nINFO listening\nWARNING: disk full\nERROR: stopped\n"[..];
+        assert_eq!(streaming.parse::<Error<_>>(log), Ok((&log[29..], (&log[..29], 1))));
+        assert_eq!(complete.parse::<Error<_>>(&log[30..]), Ok((&log[48..], (&log[30..48], 0))));
+
+        let request = &b"GET / HTTP/1.1\r\nHost: a\r\n\r\nbody"[..];
+        assert_eq!(complete.parse::<Error<_>>(request), Ok((&b"\r\n\r\nbody"[..], (&b"GET / HTTP/1.1\r\nHost: a"[..], 3))));
+
+        assert_eq!(complete.parse::<Error<_>>(&b"INFO ok"[..]), Err(Err::Error(Error::new(&b"INFO ok"[..], ErrorKind::TakeUntil))));
+        assert_eq!(streaming.parse::<Error<_>>(&b"INFO ok"[..]), Err(Err::Incomplete(Needed::Unknown)));
+        assert_eq!(streaming.parse::<Error<_>>(&b"thread panicked a"[..]), Err(Err::Incomplete(Needed::new(1))));
+        // `WARN` is declared before `WARNING`
+        assert_eq!(streaming.parse::<Error<_>>(&b"x WARN"[..]), Ok((&b"WARN"[..], (&b"x "[..], 1))));
+        assert_eq!(streaming.parse::<Error<_>>(&b"x ERR"[..]), Err(Err::Incomplete(Needed::new(2))));
+        assert_eq!(complete.parse::<Error<_>>(&b"x ERR"[..]), Err(Err::Error(Error::new(&b"x ERR"[..], ErrorKind::TakeUntil))));
+
+        // no limit on the length or the number of tags
+        let numbers: Vec<Vec<u8>> = (0..300).map(|n: u32| format!("<{}>", n).into_bytes()).collect();
+        let mut tags: Vec<&[u8]> = numbers.iter().map(|n| &n[..]).collect();
+        tags.push(b"Strict-Transport-Security");
+        let parser = complete::take_until_any(&tags);
+        let input = &b"a <299> b"[..];
+        assert_eq!(parser.parse::<Error<_>>(input), Ok((&input[2..], (&input[..2], 299))));
+        let input = &b"x: 1\r\nStrict-Transport-Security: y"[..];
+        assert_eq!(parser.parse::<Error<_>>(input), Ok((&input[6..], (&input[..6], 300))));
+    }
+
+    #[test]
+    #[should_panic(expected = "tag 1 is empty")]
+    fn take_until_any_empty_test() {
+        streaming::take_until_any(&[b"ERROR", b""]);
+    }
+
+    #[test]
+    fn take_until_any_backends_test() {
+        use crate::mode::{Complete, Streaming};
+
+        // first position where a tag matches, or may match in streaming mode
+        fn reference(tags: &[&[u8]], input: &[u8], streaming: bool) -> Scan {
+            for position in 0..input.len() {
+                let rest = &input[position..];
+                for (idx, tag) in tags.iter().enumerate() {
+                    if rest.starts_with(tag) {
+                        return Scan::Found { position, idx };
+                    }
+                    if streaming && tag.starts_with(rest) {
+                        return Scan::Incomplete(tag.len() - rest.len());
+                    }
+                }
+            }
+            Scan::NotFound
+        }
+
+        fn check<M: Mode>(tags: &[&[u8]], input: &[u8]) {
+            let parser = take_until_any_mode::<M>(tags);
+            let expected = reference(tags, input, M::STREAMING);
+            assert_eq!(parser.scan_from(input, 0), expected, "{:?}", input);
+            #[cfg(target_arch = "x86_64")]
+            if crate::cpu::has_avx2() {
+                assert_eq!(unsafe { parser.scan_avx2(input) }, expected, "{:?}", input);
+            }
+            #[cfg(target_arch = "x86_64")]
+            if crate::cpu::has_ssse3() {
+                assert_eq!(unsafe { parser.scan_ssse3(input) }, expected, "{:?}", input);
+            }
+        }
+
+        // more than 8 tags, so the buckets are shared, with fingerprints of
+        // 1 to 3 bytes
+        let tags: Vec<&[u8]> = vec![
+            b"abc", b"abd", b"bcd", b"xyz", b"x\xFFz", b"\0\0\0", b"zzzz", b"cab", b"dab", b"aaaaab", b"ba\xC3",
+        ];
+        let mut input = Vec::new();
+        let mut seed = 7u32;
+        for _ in 0..600 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            input.push(b"abcdxyz\xFF\0\xC3"[(seed >> 16) as usize % 10]);
+        }
+
+        for fingerprint in 1..=3 {
+            let tags: Vec<&[u8]> = tags.iter().map(|tag| &tag[3 - fingerprint..]).collect();
+            let mut unique: Vec<&[u8]> = Vec::new();
+            for tag in tags {
+                if !unique.contains(&tag) {
+                    unique.push(tag);
+                }
+            }
+
+            for start in (0..input.len()).step_by(7) {
+                for len in [0, 1, 2, 3, 15, 16, 17, 33, 80] {
+                    let end = std::cmp::min(start + len, input.len());
+                    check::<Streaming>(&unique, &input[start..end]);
+                    check::<Complete>(&unique, &input[start..end]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn build_error_test() {
+        use crate::mode::Complete;
+
+        let errors = |tags: &[&[u8]], no_case| MultiTagBuilder::new(tags).no_case(no_case).build::<Complete>().err();
+        assert_eq!(errors(&[b"GET", b"POST"], false), None);
+        assert_eq!(errors(&[b"GET", b"", b"POST"], false), Some(BuildError::EmptyTag { index: 1 }));
+        assert_eq!(
+            errors(&[b"GET", b"If-Modified-Since"], false),
+            Some(BuildError::TagTooLong { index: 1, len: 17 })
+        );
+        assert_eq!(errors(&[b"GET", b"PUT", b"GET"], false), Some(BuildError::DuplicateTag { first: 0, second: 2 }));
+        assert_eq!(errors(&[b"GET", b"get"], false), None);
+        assert_eq!(errors(&[b"GET", b"get"], true), Some(BuildError::DuplicateTag { first: 0, second: 1 }));
+
+        let numbers: Vec<Vec<u8>> = (0..256).map(|n: u32| n.to_string().into_bytes()).collect();
+        let tags: Vec<&[u8]> = numbers.iter().map(|n| &n[..]).collect();
+        assert_eq!(errors(&tags[..255], false), None);
+        assert_eq!(errors(&tags, false), Some(BuildError::TooManyTags { count: 256 }));
+
+        let res = MultiTagBuilder::new(&[b"GET", b"POST"]).build_values::<Complete, _>(&[1]);
+        assert_eq!(res.err(), Some(BuildError::ValueCount { tags: 2, values: 1 }));
+
+        assert_eq!(
+            BuildError::DuplicateTag { first: 0, second: 2 }.to_string(),
+            "tag 2 is the same as tag 0: tags must be unique"
+        );
+
+        // the const path checks the same conditions
+        const VALID: Result<(), BuildError> = validate(&[b"Host", b"host"], false);
+        assert_eq!(VALID, Ok(()));
+        assert_eq!(validate(&[b"Host", b"host"], true), Err(BuildError::DuplicateTag { first: 0, second: 1 }));
+    }
+
+    #[test]
+    #[should_panic(expected = "there must be one value per tag")]
+    fn multitag_values_count_test() {
+        MultiTagBuilder::new(&[b"GET", b"POST"])
+            .build_values::<crate::mode::Complete, _>(&[1])
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    #[test]
+    #[should_panic(expected = "tags must be unique")]
+    fn prepare_groups_duplicate_test() {
+        let _: [MasksVec; 1] = prepare_groups(&[b"GET", b"GET"]);
+    }
+
+    #[test]
+    fn static_multitag_test() {
+        use nom::error::Error;
+        use crate::mode::{Complete, Streaming};
+
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        enum Method {
+            Get,
+            Head,
+            Post,
+        }
+
+        static_multitag!(static HEADERS: MultiTag<Complete> = [b"Accept", b"Accept-Charset", b"Content-Length", b"Host", b"X-Forwarded-For"]);
+        static_multitag!(static LONGEST: MultiTag<Complete> = no_case [b"Accept", b"Accept-Charset"].with_policy(MatchPolicy::Longest));
+        static_multitag! {
+            /// HTTP methods
+            pub(crate) static METHODS: MultiTagValues<Method, Streaming> = [
+                b"GET" => Method::Get,
+                b"HEAD" => Method::Head,
+                b"POST" => Method::Post,
+            ];
+        }
+
+        assert_eq!(HEADERS.groups.len(), 3);
+        let runtime = complete::multitag(&[b"Accept", b"Accept-Charset", b"Content-Length", b"Host", b"X-Forwarded-For"]);
+        for input in [&b"Accept-Charset: utf-8"[..], &b"X-Forwarded-For: 1.2.3.4"[..], &b"Host"[..], &b"Server: hello"[..]].iter() {
+            assert_eq!(HEADERS.parse::<Error<_>>(input), runtime.parse(input));
+        }
+
+        assert_eq!(
+            LONGEST.parse::<Error<_>>(&b"accept-charset: utf-8"[..]),
+            Ok((&b": utf-8"[..], (1, &b"accept-charset"[..])))
+        );
+
+        assert_eq!(METHODS.parse::<Error<_>>(&b"HEAD / HTTP/1.1\r\n"[..]), Ok((&b" / HTTP/1.1\r\n"[..], Method::Head)));
+        assert_eq!(METHODS.parse::<Error<_>>(&b"HE"[..]), Err(Err::Incomplete(Needed::new(2))));
+    }
+
+    #[test]
+    fn multitag_length_test() {
+        use nom::error::Error;
+        use crate::mode::Complete;
+
+        // tags from 1 to 16 bytes, the first one ending at bit 0
+        let tags = [&b"A"[..], &b"Via:"[..], &b"X-Forwarded-For:"[..], &b"Upgrade"[..], &b"If-Modified-"[..]];
+        let parser = multitag_mode::<Complete>(&tags[..]);
+
+        for (idx, tag) in tags.iter().enumerate() {
+            for suffix in [&b""[..], &b" value"[..], &b" 1234567890abcdef"[..]].iter() {
+                let input = [tag, *suffix].concat();
+                assert_eq!(parser.parse::<Error<_>>(&input), Ok((*suffix, (idx, *tag))));
+            }
+        }
+
+        // the tag would end in the padding
+        assert_eq!(
+            parser.parse::<Error<_>>(&b"Upgr"[..]),
+            Err(Err::Error(Error::new(&b"Upgr"[..], ErrorKind::Tag)))
+        );
+        let zeroes = multitag_mode::<Complete>(&[b"ab\0\0"]);
+        assert_eq!(
+            zeroes.parse::<Error<_>>(&b"ab"[..]),
+            Err(Err::Error(Error::new(&b"ab"[..], ErrorKind::Tag)))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "tags must be at most 16 bytes long")]
+    fn multitag_long_tag_test() {
+        complete::multitag(&[b"If-Modified-Since"]);
+    }
+
+    #[test]
+    fn multitag_test() {
+        use nom::error::Error;
+        use crate::mode::{Complete, Streaming};
+
+        let tags = [&b"Acce"[..], &b"Cont"[..], &b"Date"[..], &b"Host"[..]];
+
+        let streaming = multitag_mode::<Streaming>(&tags[..]);
+        assert_eq!(streaming.parse::<Error<_>>(&b"Host: hello.com\r\n"[..]), Ok((&b": hello.com\r\n"[..], (3, &b"Host"[..]))));
+        assert_eq!(multitag(&tags[..]).parse::<Error<_>>(&b"Ho"[..]), Err(Err::Incomplete(Needed::new(2))));
+        assert_eq!(streaming.parse::<Error<_>>(&b"Host: a\r\n"[..]), Ok((&b": a\r\n"[..], (3, &b"Host"[..]))));
+        assert_eq!(streaming.parse::<Error<_>>(&b"Ho"[..]), Err(Err::Incomplete(Needed::new(2))));
+        assert_eq!(streaming.parse::<Error<_>>(&b"Hi"[..]), Err(Err::Error(Error::new(&b"Hi"[..], ErrorKind::Tag))));
+        assert_eq!(
+            streaming.parse::<Error<_>>(&b"Server: hello.com\r\n"[..]),
+            Err(Err::Error(Error::new(&b"Server: hello.com\r\n"[..], ErrorKind::Tag)))
+        );
+
+        let complete = multitag_mode::<Complete>(&tags[..]);
+        assert_eq!(complete.parse::<Error<_>>(&b"Host: hello.com\r\n"[..]), Ok((&b": hello.com\r\n"[..], (3, &b"Host"[..]))));
+        assert_eq!(complete.parse::<Error<_>>(&b"Host: a\r\n"[..]), Ok((&b": a\r\n"[..], (3, &b"Host"[..]))));
+        assert_eq!(complete.parse::<Error<_>>(&b"Date"[..]), Ok((&b""[..], (2, &b"Date"[..]))));
+        assert_eq!(complete.parse::<Error<_>>(&b"Dat"[..]), Err(Err::Error(Error::new(&b"Dat"[..], ErrorKind::Tag))));
+
+        const TAGS: &[&[u8]] = &[b"Acce", b"Cont", b"Date", b"Host"];
+        static GROUPS: [MasksVec; group_count(TAGS)] = prepare_groups(TAGS);
+        static HEADERS: MultiTag<Complete> = MultiTag::from_groups(&GROUPS);
+        let mut parser = nom::combinator::map(HEADERS.clone(), |(idx, _)| tags[idx]);
+        let res: IResult<_, _, Error<&[u8]>> = parser(&b"Content-Length: 12"[..]);
+        assert_eq!(res, Ok((&b"ent-Length: 12"[..], &b"Cont"[..])));
+
+        // the remaining inputs are compared by length
+        fn lengths<I: nom::InputLength>(res: IResult<I, (usize, &[u8]), Error<I>>) -> IResult<usize, (usize, &[u8]), Error<usize>> {
+            res.map(|(rest, output)| (rest.input_len(), output))
+                .map_err(|e| e.map(|e| Error::new(e.input.input_len(), e.code)))
+        }
+
+        let input = &b"Date: Tue, 15 Nov 1994 08:12:31 GMT\r\n"[..];
+        for len in 0..=input.len() {
+            let buf = crate::padded::PaddedBuf::from(&input[..len]);
+            assert_eq!(lengths(streaming.parse_padded(buf.input())), lengths(streaming.parse(&input[..len])));
+            assert_eq!(lengths(complete.parse_padded(buf.input())), lengths(complete.parse(&input[..len])));
+        }
     }
 }
@@ -1,576 +1,3553 @@
 /* Copyright (C) 2020 Geoffroy Couprie */
 use nom::{
     error::{ErrorKind, ParseError},
-    Err, IResult, Needed,
+    Err, IResult, Needed, Parser,
 };
 
-pub fn take_while0_unrolled<'a, F, Error: ParseError<&'a [u8]>>(
+use std::marker::PhantomData;
+
+use crate::ascii::{to_ascii_lowercase_scalar, to_ascii_lowercase_swar};
+#[cfg(target_arch = "x86_64")]
+use crate::ascii::{to_ascii_lowercase_avx2, to_ascii_lowercase_sse2};
+use crate::lut::{Ranges, Shufti};
+use crate::matcher::{Aligned16, TagMatcher};
+use crate::mode::{Mode, Streaming};
+use crate::padded::PaddedInput;
+
+/// implements `nom::Parser` for a type with an inherent `parse` method
+macro_rules! impl_parser {
+    ($ty:ty, $($generics:tt)*) => {
+        impl<'a, $($generics)*, Error: ParseError<&'a [u8]>> Parser<&'a [u8], &'a [u8], Error> for $ty {
+            fn parse(&mut self, input: &'a [u8]) -> IResult<&'a [u8], &'a [u8], Error> {
+                <$ty>::parse(self, input)
+            }
+        }
+    };
+}
+
+/// implements `nom::Parser` over a `PaddedInput` with the inherent `parse_padded` method
+///
+/// `parse_padded` behaves like `parse`, but handles the end of the input with
+/// the padding instead of a scalar loop, see the [padded](crate::padded) module
+macro_rules! impl_padded_parser {
+    ($ty:ty, $($generics:tt)*) => {
+        impl<'a, $($generics)*, Error: ParseError<PaddedInput<'a>>> Parser<PaddedInput<'a>, &'a [u8], Error> for $ty {
+            fn parse(&mut self, input: PaddedInput<'a>) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+                <$ty>::parse_padded(self, input)
+            }
+        }
+    };
+}
+
+/// returns the index of the first byte of `input` that does not satisfy `cond`,
+/// or `input.len()`
+#[inline(always)]
+fn take_while_unrolled_index<F>(cond: &F, input: &[u8]) -> usize
+where
+    F: Fn(u8) -> bool,
+{
+    let mut i = 0usize;
+    let len = input.len();
+    let mut found = false;
+
+    loop {
+        if len - i < 8 {
+            break;
+        }
+
+        if !cond(unsafe { *input.get_unchecked(i) }) {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if !cond(unsafe { *input.get_unchecked(i) }) {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if !cond(unsafe { *input.get_unchecked(i) }) {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if !cond(unsafe { *input.get_unchecked(i) }) {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if !cond(unsafe { *input.get_unchecked(i) }) {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if !cond(unsafe { *input.get_unchecked(i) }) {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if !cond(unsafe { *input.get_unchecked(i) }) {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if !cond(unsafe { *input.get_unchecked(i) }) {
+            found = true;
+            break;
+        }
+        i += 1;
+    }
+
+    if !found {
+        while i < len && cond(unsafe { *input.get_unchecked(i) }) {
+            i += 1;
+        }
+    }
+
+    i
+}
+
+#[inline(always)]
+fn take_while0_result<'a, M: Mode, Error: ParseError<&'a [u8]>>(
+    input: &'a [u8],
+    i: usize,
+) -> IResult<&'a [u8], &'a [u8], Error> {
+    // nom asks for one more byte, like `split_at_position`
+    if i == input.len() && M::STREAMING {
+        Err(Err::Incomplete(Needed::new(1)))
+    } else {
+        let (prefix, suffix) = input.split_at(i);
+        Ok((suffix, prefix))
+    }
+}
+
+#[inline(always)]
+fn take_while1_result<'a, M: Mode, Error: ParseError<&'a [u8]>>(
+    input: &'a [u8],
+    i: usize,
+    kind: ErrorKind,
+) -> IResult<&'a [u8], &'a [u8], Error> {
+    // an empty input could still be followed by matching bytes
+    if i == input.len() && M::STREAMING {
+        Err(Err::Incomplete(Needed::new(1)))
+    } else if i == 0 {
+        Err(Err::Error(Error::from_error_kind(input, kind)))
+    } else {
+        let (prefix, suffix) = input.split_at(i);
+        Ok((suffix, prefix))
+    }
+}
+
+/// converts the result of a combinator applied to the bytes of `input`
+#[inline(always)]
+fn padded_result<'a, Error: ParseError<PaddedInput<'a>>>(
+    input: PaddedInput<'a>,
+    res: IResult<&'a [u8], &'a [u8], (&'a [u8], ErrorKind)>,
+) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+    match res {
+        Ok((_, output)) => Ok(input.take_split(output.len())),
+        Err(Err::Error((_, kind))) => Err(Err::Error(Error::from_error_kind(input, kind))),
+        Err(Err::Failure((_, kind))) => Err(Err::Failure(Error::from_error_kind(input, kind))),
+        Err(Err::Incomplete(needed)) => Err(Err::Incomplete(needed)),
+    }
+}
+
+/// the part of the input `take_while_m_n` has to look at
+#[inline(always)]
+fn take_while_m_n_window(input: &[u8], m: usize, n: usize) -> &[u8] {
+    &input[..std::cmp::min(std::cmp::max(m, n), input.len())]
+}
+
+/// `i` is the index of the first rejected byte in `take_while_m_n_window`,
+/// or the length of the window
+#[inline(always)]
+fn take_while_m_n_result<'a, M: Mode, Error: ParseError<&'a [u8]>>(
+    input: &'a [u8],
+    m: usize,
+    n: usize,
+    i: usize,
+) -> IResult<&'a [u8], &'a [u8], Error> {
+    let window = std::cmp::min(std::cmp::max(m, n), input.len());
+
+    let index = if i < window {
+        // found a rejected byte
+        if i >= m {
+            std::cmp::min(i, n)
+        } else {
+            return Err(Err::Error(Error::from_error_kind(input, ErrorKind::TakeWhileMN)));
+        }
+    } else if input.len() >= n {
+        n
+    } else if M::STREAMING {
+        let needed = if m > input.len() { m - input.len() } else { 1 };
+        return Err(Err::Incomplete(Needed::new(needed)));
+    } else if input.len() >= m {
+        input.len()
+    } else {
+        return Err(Err::Error(Error::from_error_kind(input, ErrorKind::TakeWhileMN)));
+    };
+
+    let (prefix, suffix) = input.split_at(index);
+    Ok((suffix, prefix))
+}
+
+/// parser built by [`take_while0_unrolled`]
+#[derive(Clone, Copy)]
+pub struct TakeWhile0Unrolled<F, M = Streaming> {
+    cond: F,
+    mode: PhantomData<M>,
+}
+
+impl<F: Fn(u8) -> bool, M: Mode> TakeWhile0Unrolled<F, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = take_while_unrolled_index(&self.cond, input);
+        take_while0_result::<M, Error>(input, i)
+    }
+}
+
+impl_parser!(TakeWhile0Unrolled<F, M>, F: Fn(u8) -> bool, M: Mode);
+
+#[inline(always)]
+pub const fn take_while0_unrolled_mode<M: Mode, F>(
+    cond: F,
+) -> TakeWhile0Unrolled<F, M>
+where
+    F: Fn(u8) -> bool,
+{
+    TakeWhile0Unrolled {
+        cond,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_while1_unrolled`]
+#[derive(Clone, Copy)]
+pub struct TakeWhile1Unrolled<F, M = Streaming> {
+    cond: F,
+    mode: PhantomData<M>,
+}
+
+impl<F: Fn(u8) -> bool, M: Mode> TakeWhile1Unrolled<F, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = take_while_unrolled_index(&self.cond, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::TakeWhile1)
+    }
+}
+
+impl_parser!(TakeWhile1Unrolled<F, M>, F: Fn(u8) -> bool, M: Mode);
+
+#[inline(always)]
+pub const fn take_while1_unrolled_mode<M: Mode, F>(
     cond: F,
-) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], &'a [u8], Error>
+) -> TakeWhile1Unrolled<F, M>
 where
     F: Fn(u8) -> bool,
 {
-    move |input: &'a [u8]| {
-        let mut i = 0usize;
-        let len = input.len();
-        let mut found = false;
+    TakeWhile1Unrolled {
+        cond,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_while_m_n_unrolled`]
+#[derive(Clone, Copy)]
+pub struct TakeWhileMNUnrolled<F, M = Streaming> {
+    m: usize,
+    n: usize,
+    cond: F,
+    mode: PhantomData<M>,
+}
+
+impl<F: Fn(u8) -> bool, M: Mode> TakeWhileMNUnrolled<F, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let window = take_while_m_n_window(input, self.m, self.n);
+        let i = take_while_unrolled_index(&self.cond, window);
+        take_while_m_n_result::<M, Error>(input, self.m, self.n, i)
+    }
+}
+
+impl_parser!(TakeWhileMNUnrolled<F, M>, F: Fn(u8) -> bool, M: Mode);
+
+/// recognizes between `m` and `n` bytes matching `cond`, without looking
+/// further than `n` bytes
+#[inline(always)]
+pub const fn take_while_m_n_unrolled_mode<M: Mode, F>(
+    m: usize,
+    n: usize,
+    cond: F,
+) -> TakeWhileMNUnrolled<F, M>
+where
+    F: Fn(u8) -> bool,
+{
+    TakeWhileMNUnrolled {
+        m,
+        n,
+        cond,
+        mode: PhantomData,
+    }
+}
+
+/// uses SSE4.2 if available, otherwise falls back to `take_while_unrolled_index`
+#[inline(always)]
+fn take_while_ranges_index(ranges: &Ranges, input: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_sse42() {
+            return unsafe { take_while_ranges_index_sse42(ranges, input) };
+        }
+    }
+
+    take_while_unrolled_index(&|c| ranges.accepts(c), input)
+}
+
+/// same as `take_while_ranges_index`, without a scalar tail
+#[inline(always)]
+fn take_while_ranges_index_padded(ranges: &Ranges, input: PaddedInput) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_sse42() {
+            return unsafe { take_while_ranges_index_sse42_padded(ranges, input) };
+        }
+    }
+
+    take_while_unrolled_index(&|c| ranges.accepts(c), input.as_bytes())
+}
+
+/// the length of the last block is passed to `_mm_cmpestri`, so the bytes
+/// after the end of the input are ignored
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2")]
+unsafe fn take_while_ranges_index_sse42_padded(ranges: &Ranges, input: PaddedInput) -> usize {
+    use std::arch::x86_64::{
+        _mm_cmpestri, _mm_load_si128, _mm_loadu_si128, _SIDD_CMP_RANGES,
+        _SIDD_LEAST_SIGNIFICANT, _SIDD_UBYTE_OPS,
+    };
+
+    let ranges16 = _mm_load_si128(ranges.padded().0.as_ptr() as *const _);
+    let ranges_len = ranges.len() as i32;
+    let data = input.with_padding().as_ptr();
+    let len = input.len();
+
+    let mut i = 0usize;
+    while i < len {
+        let block_len = std::cmp::min(len - i, 16);
+        let idx = _mm_cmpestri(
+            ranges16,
+            ranges_len,
+            _mm_loadu_si128(data.add(i) as *const _),
+            block_len as i32,
+            _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_RANGES | _SIDD_UBYTE_OPS,
+        ) as usize;
+
+        if idx < block_len {
+            return i + idx;
+        }
+
+        i += block_len;
+    }
+
+    len
+}
 
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2")]
+unsafe fn take_while_ranges_index_sse42(ranges: &Ranges, input: &[u8]) -> usize {
+    use std::arch::x86_64::{
+        _mm_cmpestri, _mm_load_si128, _mm_loadu_si128, _SIDD_CMP_RANGES,
+        _SIDD_LEAST_SIGNIFICANT, _SIDD_UBYTE_OPS,
+    };
+
+    let start = input.as_ptr() as usize;
+    let mut i = input.as_ptr() as usize;
+    let mut left = input.len();
+    let mut found = false;
+
+    if left >= 16 {
+        let ranges16 = _mm_load_si128(ranges.padded().0.as_ptr() as *const _);
+        let ranges_len = ranges.len() as i32;
         loop {
-            if len - i < 8 {
+            let sl = _mm_loadu_si128(i as *const _);
+
+            let idx = _mm_cmpestri(
+                ranges16,
+                ranges_len,
+                sl,
+                16,
+                _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_RANGES | _SIDD_UBYTE_OPS,
+            );
+
+            if idx != 16 {
+                i += idx as usize;
+                found = true;
+                break;
+            }
+
+            i += 16;
+            left -= 16;
+
+            if left < 16 {
                 break;
             }
+        }
+    }
+
+    let mut i = i - start;
+    if !found {
+        while i < input.len() && ranges.accepts(*input.get_unchecked(i)) {
+            i += 1;
+        }
+    }
+
+    i
+}
+
+/// parser built by [`take_while0_sse2`]
+#[derive(Clone, Copy)]
+pub struct TakeWhile0Sse2<M = Streaming> {
+    ranges: Ranges,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TakeWhile0Sse2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = take_while_ranges_index(&self.ranges, input);
+        take_while0_result::<M, Error>(input, i)
+    }
+}
+
+impl_parser!(TakeWhile0Sse2<M>, M: Mode);
+
+impl<M: Mode> TakeWhile0Sse2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = take_while_ranges_index_padded(&self.ranges, input);
+        padded_result(input, take_while0_result::<M, _>(input.as_bytes(), i))
+    }
+}
+
+impl_padded_parser!(TakeWhile0Sse2<M>, M: Mode);
+
+/// recognizes the bytes accepted by the predicate `ranges` was built from,
+/// with `make_ranges!(predicate)`
+///
+/// the SIMD loop stops at the bytes in `ranges`, and the scalar code uses
+/// the lookup table they were computed from, so they always agree
+#[inline(always)]
+pub const fn take_while0_sse2_mode<M: Mode>(
+    ranges: Ranges,
+) -> TakeWhile0Sse2<M> {
+    TakeWhile0Sse2 {
+        ranges,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_while1_sse2`]
+#[derive(Clone, Copy)]
+pub struct TakeWhile1Sse2<M = Streaming> {
+    ranges: Ranges,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TakeWhile1Sse2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = take_while_ranges_index(&self.ranges, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::TakeWhile1)
+    }
+}
+
+impl_parser!(TakeWhile1Sse2<M>, M: Mode);
+
+impl<M: Mode> TakeWhile1Sse2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = take_while_ranges_index_padded(&self.ranges, input);
+        padded_result(input, take_while1_result::<M, _>(input.as_bytes(), i, ErrorKind::TakeWhile1))
+    }
+}
+
+impl_padded_parser!(TakeWhile1Sse2<M>, M: Mode);
+
+/// same as [`take_while0_sse2`], with at least one byte
+#[inline(always)]
+pub const fn take_while1_sse2_mode<M: Mode>(
+    ranges: Ranges,
+) -> TakeWhile1Sse2<M> {
+    TakeWhile1Sse2 {
+        ranges,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_while_m_n_sse2`]
+#[derive(Clone, Copy)]
+pub struct TakeWhileMNSse2<M = Streaming> {
+    m: usize,
+    n: usize,
+    ranges: Ranges,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TakeWhileMNSse2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let window = take_while_m_n_window(input, self.m, self.n);
+        let i = take_while_ranges_index(&self.ranges, window);
+        take_while_m_n_result::<M, Error>(input, self.m, self.n, i)
+    }
+}
+
+impl_parser!(TakeWhileMNSse2<M>, M: Mode);
+
+impl<M: Mode> TakeWhileMNSse2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let window = take_while_m_n_window(input.as_bytes(), self.m, self.n).len();
+        let i = take_while_ranges_index_padded(&self.ranges, nom::InputTake::take(&input, window));
+        padded_result(input, take_while_m_n_result::<M, _>(input.as_bytes(), self.m, self.n, i))
+    }
+}
+
+impl_padded_parser!(TakeWhileMNSse2<M>, M: Mode);
+
+/// same as [`take_while0_sse2`], with between `m` and `n` bytes
+#[inline(always)]
+pub const fn take_while_m_n_sse2_mode<M: Mode>(
+    m: usize,
+    n: usize,
+    ranges: Ranges,
+) -> TakeWhileMNSse2<M> {
+    TakeWhileMNSse2 {
+        m,
+        n,
+        ranges,
+        mode: PhantomData,
+    }
+}
+
+/// sets the bits of the positions past the end of the input, in a block
+/// starting `remaining` bytes before the end
+#[inline(always)]
+fn past_end_mask(remaining: usize) -> u32 {
+    if remaining >= 32 {
+        0
+    } else {
+        !0u32 << remaining
+    }
+}
+
+/// uses AVX2 if available, otherwise falls back to `take_while_ranges_index`
+#[inline(always)]
+fn take_while_ranges_index_wide(ranges: &Ranges, input: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_avx2() {
+            return unsafe { take_while_ranges_index_avx2::<false>(ranges, input, input.len()) };
+        }
+    }
+
+    take_while_ranges_index(ranges, input)
+}
+
+/// same as `take_while_ranges_index_wide`, without a scalar tail
+#[inline(always)]
+fn take_while_ranges_index_wide_padded(ranges: &Ranges, input: PaddedInput) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_avx2() {
+            return unsafe {
+                take_while_ranges_index_avx2::<true>(ranges, input.with_padding(), input.len())
+            };
+        }
+    }
+
+    take_while_ranges_index_padded(ranges, input)
+}
+
+/// classifies 32 bytes per iteration: a byte `c` is in the range `[lo, hi]`
+/// if `max(c, lo) == c` and `min(c, hi) == c`
+///
+/// `len` is the length of the input. With `PADDED`, `input` must contain 32
+/// more bytes, and the last block is loaded entirely and masked instead of
+/// going through a scalar loop
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn take_while_ranges_index_avx2<const PADDED: bool>(
+    ranges: &Ranges,
+    input: &[u8],
+    len: usize,
+) -> usize {
+    use std::arch::x86_64::{
+        __m256i, _mm256_and_si256, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_max_epu8,
+        _mm256_min_epu8, _mm256_movemask_epi8, _mm256_or_si256, _mm256_set1_epi8,
+        _mm256_setzero_si256,
+    };
+
+    let mut low = [_mm256_setzero_si256(); 8];
+    let mut high = [_mm256_setzero_si256(); 8];
+    let mut ranges_count = 0;
+    for range in ranges.as_bytes().chunks_exact(2) {
+        low[ranges_count] = _mm256_set1_epi8(range[0] as i8);
+        high[ranges_count] = _mm256_set1_epi8(range[1] as i8);
+        ranges_count += 1;
+    }
+
+    debug_assert!(input.len() >= if PADDED { len + 32 } else { len });
+
+    let mut i = 0usize;
+    while if PADDED { i < len } else { len - i >= 32 } {
+        let sl = _mm256_loadu_si256(input.as_ptr().add(i) as *const __m256i);
+
+        let mut rejected = _mm256_setzero_si256();
+        for r in 0..ranges_count {
+            let above = _mm256_cmpeq_epi8(_mm256_max_epu8(sl, low[r]), sl);
+            let below = _mm256_cmpeq_epi8(_mm256_min_epu8(sl, high[r]), sl);
+            rejected = _mm256_or_si256(rejected, _mm256_and_si256(above, below));
+        }
+
+        let mut mask = _mm256_movemask_epi8(rejected) as u32;
+        if PADDED {
+            mask |= past_end_mask(len - i);
+        }
+        if mask != 0 {
+            return i + mask.trailing_zeros() as usize;
+        }
+
+        i += 32;
+    }
+
+    while i < len && ranges.accepts(*input.get_unchecked(i)) {
+        i += 1;
+    }
+
+    i
+}
+
+/// parser built by [`take_while0_avx2`]
+#[derive(Clone, Copy)]
+pub struct TakeWhile0Avx2<M = Streaming> {
+    ranges: Ranges,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TakeWhile0Avx2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = take_while_ranges_index_wide(&self.ranges, input);
+        take_while0_result::<M, Error>(input, i)
+    }
+}
+
+impl_parser!(TakeWhile0Avx2<M>, M: Mode);
+
+impl<M: Mode> TakeWhile0Avx2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = take_while_ranges_index_wide_padded(&self.ranges, input);
+        padded_result(input, take_while0_result::<M, _>(input.as_bytes(), i))
+    }
+}
+
+impl_padded_parser!(TakeWhile0Avx2<M>, M: Mode);
+
+/// same as [`take_while0_sse2`], using AVX2 if available
+#[inline(always)]
+pub const fn take_while0_avx2_mode<M: Mode>(
+    ranges: Ranges,
+) -> TakeWhile0Avx2<M> {
+    TakeWhile0Avx2 {
+        ranges,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_while1_avx2`]
+#[derive(Clone, Copy)]
+pub struct TakeWhile1Avx2<M = Streaming> {
+    ranges: Ranges,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TakeWhile1Avx2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = take_while_ranges_index_wide(&self.ranges, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::TakeWhile1)
+    }
+}
+
+impl_parser!(TakeWhile1Avx2<M>, M: Mode);
+
+impl<M: Mode> TakeWhile1Avx2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = take_while_ranges_index_wide_padded(&self.ranges, input);
+        padded_result(input, take_while1_result::<M, _>(input.as_bytes(), i, ErrorKind::TakeWhile1))
+    }
+}
+
+impl_padded_parser!(TakeWhile1Avx2<M>, M: Mode);
+
+/// same as [`take_while1_sse2`], using AVX2 if available
+#[inline(always)]
+pub const fn take_while1_avx2_mode<M: Mode>(
+    ranges: Ranges,
+) -> TakeWhile1Avx2<M> {
+    TakeWhile1Avx2 {
+        ranges,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_while_m_n_avx2`]
+#[derive(Clone, Copy)]
+pub struct TakeWhileMNAvx2<M = Streaming> {
+    m: usize,
+    n: usize,
+    ranges: Ranges,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TakeWhileMNAvx2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let window = take_while_m_n_window(input, self.m, self.n);
+        let i = take_while_ranges_index_wide(&self.ranges, window);
+        take_while_m_n_result::<M, Error>(input, self.m, self.n, i)
+    }
+}
+
+impl_parser!(TakeWhileMNAvx2<M>, M: Mode);
+
+impl<M: Mode> TakeWhileMNAvx2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let window = take_while_m_n_window(input.as_bytes(), self.m, self.n).len();
+        let i = take_while_ranges_index_wide_padded(&self.ranges, nom::InputTake::take(&input, window));
+        padded_result(input, take_while_m_n_result::<M, _>(input.as_bytes(), self.m, self.n, i))
+    }
+}
+
+impl_padded_parser!(TakeWhileMNAvx2<M>, M: Mode);
+
+/// same as [`take_while_m_n_sse2`], using AVX2 if available
+#[inline(always)]
+pub const fn take_while_m_n_avx2_mode<M: Mode>(
+    m: usize,
+    n: usize,
+    ranges: Ranges,
+) -> TakeWhileMNAvx2<M> {
+    TakeWhileMNAvx2 {
+        m,
+        n,
+        ranges,
+        mode: PhantomData,
+    }
+}
+
+/// returns the index of the first byte for which `set.contains(c) != accept`
+///
+/// uses AVX2 or SSSE3 if available, otherwise falls back to `take_while_unrolled_index`
+#[inline(always)]
+fn shufti_index(set: &Shufti, accept: bool, input: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_avx2() {
+            return unsafe { shufti_index_avx2::<false>(set, accept, input, input.len()) };
+        }
+        if crate::cpu::has_ssse3() {
+            return unsafe { shufti_index_ssse3::<false>(set, accept, input, input.len()) };
+        }
+    }
+
+    take_while_unrolled_index(&|c| set.contains(c) == accept, input)
+}
+
+/// same as `shufti_index`, without a scalar tail
+#[inline(always)]
+fn shufti_index_padded(set: &Shufti, accept: bool, input: PaddedInput) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let (data, len) = (input.with_padding(), input.len());
+        if crate::cpu::has_avx2() {
+            return unsafe { shufti_index_avx2::<true>(set, accept, data, len) };
+        }
+        if crate::cpu::has_ssse3() {
+            return unsafe { shufti_index_ssse3::<true>(set, accept, data, len) };
+        }
+    }
+
+    take_while_unrolled_index(&|c| set.contains(c) == accept, input.as_bytes())
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+unsafe fn shufti_index_ssse3<const PADDED: bool>(set: &Shufti, accept: bool, input: &[u8], len: usize) -> usize {
+    use std::arch::x86_64::{
+        __m128i, _mm_and_si128, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8,
+        _mm_or_si128, _mm_set1_epi8, _mm_setzero_si128, _mm_shuffle_epi8, _mm_srli_epi16,
+    };
+
+    let lo0 = _mm_loadu_si128(set.lo()[0].as_ptr() as *const __m128i);
+    let lo1 = _mm_loadu_si128(set.lo()[1].as_ptr() as *const __m128i);
+    let hi0 = _mm_loadu_si128(set.hi()[0].as_ptr() as *const __m128i);
+    let hi1 = _mm_loadu_si128(set.hi()[1].as_ptr() as *const __m128i);
+    let nibble = _mm_set1_epi8(0x0F);
+    let zero = _mm_setzero_si128();
+    let flip = if accept { 0 } else { 0xFFFF };
+
+    debug_assert!(input.len() >= if PADDED { len + 16 } else { len });
+
+    let mut i = 0usize;
+    while if PADDED { i < len } else { len - i >= 16 } {
+        let sl = _mm_loadu_si128(input.as_ptr().add(i) as *const __m128i);
+        let low = _mm_and_si128(sl, nibble);
+        let high = _mm_and_si128(_mm_srli_epi16(sl, 4), nibble);
+
+        let class = _mm_or_si128(
+            _mm_and_si128(_mm_shuffle_epi8(lo0, low), _mm_shuffle_epi8(hi0, high)),
+            _mm_and_si128(_mm_shuffle_epi8(lo1, low), _mm_shuffle_epi8(hi1, high)),
+        );
+
+        // bytes outside of the set, or inside of it for `take_till`
+        let mut mask = (_mm_movemask_epi8(_mm_cmpeq_epi8(class, zero)) as u32) ^ flip;
+        if PADDED {
+            // the block is only 16 bytes wide
+            mask |= past_end_mask(len - i) & 0xFFFF;
+        }
+        if mask != 0 {
+            return i + mask.trailing_zeros() as usize;
+        }
+
+        i += 16;
+    }
+
+    while i < len && set.contains(*input.get_unchecked(i)) == accept {
+        i += 1;
+    }
+
+    i
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn shufti_index_avx2<const PADDED: bool>(set: &Shufti, accept: bool, input: &[u8], len: usize) -> usize {
+    use std::arch::x86_64::{
+        __m128i, __m256i, _mm256_and_si256, _mm256_broadcastsi128_si256, _mm256_cmpeq_epi8,
+        _mm256_loadu_si256, _mm256_movemask_epi8, _mm256_or_si256, _mm256_set1_epi8,
+        _mm256_setzero_si256, _mm256_shuffle_epi8, _mm256_srli_epi16, _mm_loadu_si128,
+    };
+
+    // the shuffle works in each 128 bits lane, so the tables are duplicated
+    let lo0 = _mm256_broadcastsi128_si256(_mm_loadu_si128(set.lo()[0].as_ptr() as *const __m128i));
+    let lo1 = _mm256_broadcastsi128_si256(_mm_loadu_si128(set.lo()[1].as_ptr() as *const __m128i));
+    let hi0 = _mm256_broadcastsi128_si256(_mm_loadu_si128(set.hi()[0].as_ptr() as *const __m128i));
+    let hi1 = _mm256_broadcastsi128_si256(_mm_loadu_si128(set.hi()[1].as_ptr() as *const __m128i));
+    let nibble = _mm256_set1_epi8(0x0F);
+    let zero = _mm256_setzero_si256();
+    let flip = if accept { 0 } else { u32::MAX };
+
+    debug_assert!(input.len() >= if PADDED { len + 32 } else { len });
+
+    let mut i = 0usize;
+    while if PADDED { i < len } else { len - i >= 32 } {
+        let sl = _mm256_loadu_si256(input.as_ptr().add(i) as *const __m256i);
+        let low = _mm256_and_si256(sl, nibble);
+        let high = _mm256_and_si256(_mm256_srli_epi16(sl, 4), nibble);
+
+        let class = _mm256_or_si256(
+            _mm256_and_si256(_mm256_shuffle_epi8(lo0, low), _mm256_shuffle_epi8(hi0, high)),
+            _mm256_and_si256(_mm256_shuffle_epi8(lo1, low), _mm256_shuffle_epi8(hi1, high)),
+        );
+
+        let mut mask = (_mm256_movemask_epi8(_mm256_cmpeq_epi8(class, zero)) as u32) ^ flip;
+        if PADDED {
+            mask |= past_end_mask(len - i);
+        }
+        if mask != 0 {
+            return i + mask.trailing_zeros() as usize;
+        }
+
+        i += 32;
+    }
+
+    while i < len && set.contains(*input.get_unchecked(i)) == accept {
+        i += 1;
+    }
+
+    i
+}
+
+/// parser built by [`take_while0_shufti`]
+#[derive(Clone, Copy)]
+pub struct TakeWhile0Shufti<'b, M = Streaming> {
+    set: &'b Shufti,
+    mode: PhantomData<M>,
+}
+
+impl<'b, M: Mode> TakeWhile0Shufti<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = shufti_index(self.set, true, input);
+        take_while0_result::<M, Error>(input, i)
+    }
+}
+
+impl_parser!(TakeWhile0Shufti<'b, M>, 'b, M: Mode);
+
+impl<'b, M: Mode> TakeWhile0Shufti<'b, M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = shufti_index_padded(self.set, true, input);
+        padded_result(input, take_while0_result::<M, _>(input.as_bytes(), i))
+    }
+}
+
+impl_padded_parser!(TakeWhile0Shufti<'b, M>, 'b, M: Mode);
+
+/// recognizes the longest prefix made of bytes from `set`, see [make_shufti](crate::make_shufti)
+#[inline(always)]
+pub const fn take_while0_shufti_mode<'b, M: Mode>(
+    set: &'b Shufti,
+) -> TakeWhile0Shufti<'b, M> {
+    TakeWhile0Shufti {
+        set,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_while1_shufti`]
+#[derive(Clone, Copy)]
+pub struct TakeWhile1Shufti<'b, M = Streaming> {
+    set: &'b Shufti,
+    mode: PhantomData<M>,
+}
+
+impl<'b, M: Mode> TakeWhile1Shufti<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = shufti_index(self.set, true, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::TakeWhile1)
+    }
+}
+
+impl_parser!(TakeWhile1Shufti<'b, M>, 'b, M: Mode);
+
+impl<'b, M: Mode> TakeWhile1Shufti<'b, M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = shufti_index_padded(self.set, true, input);
+        padded_result(input, take_while1_result::<M, _>(input.as_bytes(), i, ErrorKind::TakeWhile1))
+    }
+}
+
+impl_padded_parser!(TakeWhile1Shufti<'b, M>, 'b, M: Mode);
+
+/// recognizes the longest non empty prefix made of bytes from `set`, see [make_shufti](crate::make_shufti)
+#[inline(always)]
+pub const fn take_while1_shufti_mode<'b, M: Mode>(
+    set: &'b Shufti,
+) -> TakeWhile1Shufti<'b, M> {
+    TakeWhile1Shufti {
+        set,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_while_m_n_shufti`]
+#[derive(Clone, Copy)]
+pub struct TakeWhileMNShufti<'b, M = Streaming> {
+    m: usize,
+    n: usize,
+    set: &'b Shufti,
+    mode: PhantomData<M>,
+}
+
+impl<'b, M: Mode> TakeWhileMNShufti<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let window = take_while_m_n_window(input, self.m, self.n);
+        let i = shufti_index(self.set, true, window);
+        take_while_m_n_result::<M, Error>(input, self.m, self.n, i)
+    }
+}
+
+impl_parser!(TakeWhileMNShufti<'b, M>, 'b, M: Mode);
+
+impl<'b, M: Mode> TakeWhileMNShufti<'b, M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let window = take_while_m_n_window(input.as_bytes(), self.m, self.n).len();
+        let i = shufti_index_padded(self.set, true, nom::InputTake::take(&input, window));
+        padded_result(input, take_while_m_n_result::<M, _>(input.as_bytes(), self.m, self.n, i))
+    }
+}
+
+impl_padded_parser!(TakeWhileMNShufti<'b, M>, 'b, M: Mode);
+
+/// recognizes between `m` and `n` bytes from `set`, see [make_shufti](crate::make_shufti)
+#[inline(always)]
+pub const fn take_while_m_n_shufti_mode<'b, M: Mode>(
+    m: usize,
+    n: usize,
+    set: &'b Shufti,
+) -> TakeWhileMNShufti<'b, M> {
+    TakeWhileMNShufti {
+        m,
+        n,
+        set,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_till_unrolled`]
+#[derive(Clone, Copy)]
+pub struct TakeTillUnrolled<F, M = Streaming> {
+    cond: F,
+    mode: PhantomData<M>,
+}
+
+impl<F: Fn(u8) -> bool, M: Mode> TakeTillUnrolled<F, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = take_while_unrolled_index(&|c| !(self.cond)(c), input);
+        take_while0_result::<M, Error>(input, i)
+    }
+}
+
+impl_parser!(TakeTillUnrolled<F, M>, F: Fn(u8) -> bool, M: Mode);
+
+#[inline(always)]
+pub const fn take_till_unrolled_mode<M: Mode, F>(
+    cond: F,
+) -> TakeTillUnrolled<F, M>
+where
+    F: Fn(u8) -> bool,
+{
+    TakeTillUnrolled {
+        cond,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_till1_unrolled`]
+#[derive(Clone, Copy)]
+pub struct TakeTill1Unrolled<F, M = Streaming> {
+    cond: F,
+    mode: PhantomData<M>,
+}
+
+impl<F: Fn(u8) -> bool, M: Mode> TakeTill1Unrolled<F, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = take_while_unrolled_index(&|c| !(self.cond)(c), input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::TakeTill1)
+    }
+}
+
+impl_parser!(TakeTill1Unrolled<F, M>, F: Fn(u8) -> bool, M: Mode);
+
+#[inline(always)]
+pub const fn take_till1_unrolled_mode<M: Mode, F>(
+    cond: F,
+) -> TakeTill1Unrolled<F, M>
+where
+    F: Fn(u8) -> bool,
+{
+    TakeTill1Unrolled {
+        cond,
+        mode: PhantomData,
+    }
+}
+
+/// set of bytes for `is_a` and `is_not`
+#[derive(Clone)]
+struct ByteSet {
+    // padded to 16 bytes so the SIMD load does not read past the end
+    chars: [u8; 16],
+    len: usize,
+    table: [bool; 256],
+}
+
+impl ByteSet {
+    fn new(chars: &[u8]) -> ByteSet {
+        let mut set = ByteSet {
+            chars: [0u8; 16],
+            len: chars.len(),
+            table: [false; 256],
+        };
+
+        for &c in chars.iter() {
+            set.table[c as usize] = true;
+        }
+        if chars.len() <= 16 {
+            set.chars[..chars.len()].copy_from_slice(chars);
+        }
+
+        set
+    }
+
+    /// sets larger than 16 bytes only use the lookup table
+    #[inline(always)]
+    fn is_small(&self) -> bool {
+        self.len <= 16
+    }
+}
+
+/// returns the index of the first byte for which `set.table[c] != accept`
+#[inline(always)]
+fn byte_set_index_unrolled(set: &ByteSet, accept: bool, input: &[u8]) -> usize {
+    take_while_unrolled_index(&|c| set.table[c as usize] == accept, input)
+}
+
+/// uses SSE4.2 if available, otherwise falls back to `byte_set_index_unrolled`
+#[inline(always)]
+fn byte_set_index_sse2(set: &ByteSet, accept: bool, input: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if set.is_small() && crate::cpu::has_sse42() {
+            return unsafe { byte_set_index_sse42::<false>(set, accept, input, input.len()) };
+        }
+    }
+
+    byte_set_index_unrolled(set, accept, input)
+}
+
+/// same as `byte_set_index_sse2`, without a scalar tail
+#[inline(always)]
+fn byte_set_index_sse2_padded(set: &ByteSet, accept: bool, input: PaddedInput) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if set.is_small() && crate::cpu::has_sse42() {
+            return unsafe { byte_set_index_sse42::<true>(set, accept, input.with_padding(), input.len()) };
+        }
+    }
+
+    byte_set_index_unrolled(set, accept, input.as_bytes())
+}
+
+/// `len` is the length of the input. With `PADDED`, `input` must contain 16
+/// more bytes, and the length of the last block is passed to `_mm_cmpestri`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2")]
+unsafe fn byte_set_index_sse42<const PADDED: bool>(set: &ByteSet, accept: bool, input: &[u8], len: usize) -> usize {
+    use std::arch::x86_64::{
+        __m128i, _mm_cmpestri, _mm_loadu_si128, _SIDD_CMP_EQUAL_ANY, _SIDD_LEAST_SIGNIFICANT,
+        _SIDD_NEGATIVE_POLARITY, _SIDD_UBYTE_OPS,
+    };
+
+    let chars = _mm_loadu_si128(set.chars.as_ptr() as *const __m128i);
+    let chars_len = set.len as i32;
+
+    debug_assert!(input.len() >= if PADDED { len + 16 } else { len });
+
+    let mut i = 0usize;
+    while if PADDED { i < len } else { len - i >= 16 } {
+        let block_len = std::cmp::min(len - i, 16);
+        let sl = _mm_loadu_si128(input.as_ptr().add(i) as *const __m128i);
+
+        // is_a stops on the first byte outside of the set, is_not on the first one inside
+        let idx = if accept {
+            _mm_cmpestri(
+                chars,
+                chars_len,
+                sl,
+                block_len as i32,
+                _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_EQUAL_ANY | _SIDD_UBYTE_OPS | _SIDD_NEGATIVE_POLARITY,
+            )
+        } else {
+            _mm_cmpestri(
+                chars,
+                chars_len,
+                sl,
+                block_len as i32,
+                _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_EQUAL_ANY | _SIDD_UBYTE_OPS,
+            )
+        } as usize;
+
+        // with the negative polarity, the positions past `block_len` are set too
+        if idx < block_len {
+            return i + idx;
+        }
+
+        i += block_len;
+    }
+
+    while i < len && set.table[*input.get_unchecked(i) as usize] == accept {
+        i += 1;
+    }
+
+    i
+}
+
+/// uses AVX2 if available, otherwise falls back to `byte_set_index_sse2`
+#[inline(always)]
+fn byte_set_index_avx2(set: &ByteSet, accept: bool, input: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if set.is_small() && crate::cpu::has_avx2() {
+            return unsafe { byte_set_index_avx2_cmpeq::<false>(set, accept, input, input.len()) };
+        }
+    }
+
+    byte_set_index_sse2(set, accept, input)
+}
+
+/// same as `byte_set_index_avx2`, without a scalar tail
+#[inline(always)]
+fn byte_set_index_avx2_padded(set: &ByteSet, accept: bool, input: PaddedInput) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if set.is_small() && crate::cpu::has_avx2() {
+            return unsafe { byte_set_index_avx2_cmpeq::<true>(set, accept, input.with_padding(), input.len()) };
+        }
+    }
+
+    byte_set_index_sse2_padded(set, accept, input)
+}
+
+/// compares 32 bytes at a time with each byte of the set
+///
+/// `len` is the length of the input. With `PADDED`, `input` must contain 32
+/// more bytes, and the last block is loaded entirely and masked
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn byte_set_index_avx2_cmpeq<const PADDED: bool>(set: &ByteSet, accept: bool, input: &[u8], len: usize) -> usize {
+    use std::arch::x86_64::{
+        __m256i, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_movemask_epi8, _mm256_or_si256,
+        _mm256_set1_epi8, _mm256_setzero_si256,
+    };
+
+    let mut chars = [_mm256_setzero_si256(); 16];
+    for (v, &c) in chars.iter_mut().zip(set.chars[..set.len].iter()) {
+        *v = _mm256_set1_epi8(c as i8);
+    }
+    let chars = &chars[..set.len];
+    let flip = if accept { u32::MAX } else { 0 };
+
+    debug_assert!(input.len() >= if PADDED { len + 32 } else { len });
+
+    let mut i = 0usize;
+    while if PADDED { i < len } else { len - i >= 32 } {
+        let sl = _mm256_loadu_si256(input.as_ptr().add(i) as *const __m256i);
+
+        let mut found = _mm256_setzero_si256();
+        for c in chars.iter() {
+            found = _mm256_or_si256(found, _mm256_cmpeq_epi8(sl, *c));
+        }
+
+        let mut mask = (_mm256_movemask_epi8(found) as u32) ^ flip;
+        if PADDED {
+            mask |= past_end_mask(len - i);
+        }
+        if mask != 0 {
+            return i + mask.trailing_zeros() as usize;
+        }
+
+        i += 32;
+    }
+
+    while i < len && set.table[*input.get_unchecked(i) as usize] == accept {
+        i += 1;
+    }
+
+    i
+}
+
+/// parser built by [`is_a_unrolled`]
+#[derive(Clone)]
+pub struct IsAUnrolled<M = Streaming> {
+    set: ByteSet,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> IsAUnrolled<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = byte_set_index_unrolled(&self.set, true, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::IsA)
+    }
+}
+
+impl_parser!(IsAUnrolled<M>, M: Mode);
+
+/// recognizes the longest non empty prefix made of bytes from `chars`
+#[inline(always)]
+pub fn is_a_unrolled_mode<M: Mode>(
+    chars: &[u8],
+) -> IsAUnrolled<M> {
+    IsAUnrolled {
+        set: ByteSet::new(chars),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`is_not_unrolled`]
+#[derive(Clone)]
+pub struct IsNotUnrolled<M = Streaming> {
+    set: ByteSet,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> IsNotUnrolled<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = byte_set_index_unrolled(&self.set, false, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::IsNot)
+    }
+}
+
+impl_parser!(IsNotUnrolled<M>, M: Mode);
+
+/// recognizes the longest non empty prefix without bytes from `chars`
+#[inline(always)]
+pub fn is_not_unrolled_mode<M: Mode>(
+    chars: &[u8],
+) -> IsNotUnrolled<M> {
+    IsNotUnrolled {
+        set: ByteSet::new(chars),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`is_a_sse2`]
+#[derive(Clone)]
+pub struct IsASse2<M = Streaming> {
+    set: ByteSet,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> IsASse2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = byte_set_index_sse2(&self.set, true, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::IsA)
+    }
+}
+
+impl_parser!(IsASse2<M>, M: Mode);
+
+impl<M: Mode> IsASse2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = byte_set_index_sse2_padded(&self.set, true, input);
+        padded_result(input, take_while1_result::<M, _>(input.as_bytes(), i, ErrorKind::IsA))
+    }
+}
+
+impl_padded_parser!(IsASse2<M>, M: Mode);
+
+/// uses `_SIDD_CMP_EQUAL_ANY` for sets up to 16 bytes
+#[inline(always)]
+pub fn is_a_sse2_mode<M: Mode>(
+    chars: &[u8],
+) -> IsASse2<M> {
+    IsASse2 {
+        set: ByteSet::new(chars),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`is_not_sse2`]
+#[derive(Clone)]
+pub struct IsNotSse2<M = Streaming> {
+    set: ByteSet,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> IsNotSse2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = byte_set_index_sse2(&self.set, false, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::IsNot)
+    }
+}
+
+impl_parser!(IsNotSse2<M>, M: Mode);
+
+impl<M: Mode> IsNotSse2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = byte_set_index_sse2_padded(&self.set, false, input);
+        padded_result(input, take_while1_result::<M, _>(input.as_bytes(), i, ErrorKind::IsNot))
+    }
+}
+
+impl_padded_parser!(IsNotSse2<M>, M: Mode);
+
+/// uses `_SIDD_CMP_EQUAL_ANY` for sets up to 16 bytes
+#[inline(always)]
+pub fn is_not_sse2_mode<M: Mode>(
+    chars: &[u8],
+) -> IsNotSse2<M> {
+    IsNotSse2 {
+        set: ByteSet::new(chars),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`is_a_avx2`]
+#[derive(Clone)]
+pub struct IsAAvx2<M = Streaming> {
+    set: ByteSet,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> IsAAvx2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = byte_set_index_avx2(&self.set, true, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::IsA)
+    }
+}
+
+impl_parser!(IsAAvx2<M>, M: Mode);
+
+impl<M: Mode> IsAAvx2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = byte_set_index_avx2_padded(&self.set, true, input);
+        padded_result(input, take_while1_result::<M, _>(input.as_bytes(), i, ErrorKind::IsA))
+    }
+}
+
+impl_padded_parser!(IsAAvx2<M>, M: Mode);
+
+/// one vector compare per byte of the set, for sets up to 16 bytes
+#[inline(always)]
+pub fn is_a_avx2_mode<M: Mode>(
+    chars: &[u8],
+) -> IsAAvx2<M> {
+    IsAAvx2 {
+        set: ByteSet::new(chars),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`is_not_avx2`]
+#[derive(Clone)]
+pub struct IsNotAvx2<M = Streaming> {
+    set: ByteSet,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> IsNotAvx2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = byte_set_index_avx2(&self.set, false, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::IsNot)
+    }
+}
+
+impl_parser!(IsNotAvx2<M>, M: Mode);
+
+impl<M: Mode> IsNotAvx2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = byte_set_index_avx2_padded(&self.set, false, input);
+        padded_result(input, take_while1_result::<M, _>(input.as_bytes(), i, ErrorKind::IsNot))
+    }
+}
+
+impl_padded_parser!(IsNotAvx2<M>, M: Mode);
+
+/// one vector compare per byte of the set, for sets up to 16 bytes
+#[inline(always)]
+pub fn is_not_avx2_mode<M: Mode>(
+    chars: &[u8],
+) -> IsNotAvx2<M> {
+    IsNotAvx2 {
+        set: ByteSet::new(chars),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_till_sse2`]
+#[derive(Clone)]
+pub struct TakeTillSse2<M = Streaming> {
+    set: ByteSet,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TakeTillSse2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = byte_set_index_sse2(&self.set, false, input);
+        take_while0_result::<M, Error>(input, i)
+    }
+}
+
+impl_parser!(TakeTillSse2<M>, M: Mode);
+
+impl<M: Mode> TakeTillSse2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = byte_set_index_sse2_padded(&self.set, false, input);
+        padded_result(input, take_while0_result::<M, _>(input.as_bytes(), i))
+    }
+}
+
+impl_padded_parser!(TakeTillSse2<M>, M: Mode);
+
+/// recognizes the longest prefix without bytes from `chars`, like
+/// `take_till(|c| chars.contains(&c))`, uses `_SIDD_CMP_EQUAL_ANY` for sets
+/// up to 16 bytes
+#[inline(always)]
+pub fn take_till_sse2_mode<M: Mode>(
+    chars: &[u8],
+) -> TakeTillSse2<M> {
+    TakeTillSse2 {
+        set: ByteSet::new(chars),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_till1_sse2`]
+#[derive(Clone)]
+pub struct TakeTill1Sse2<M = Streaming> {
+    set: ByteSet,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TakeTill1Sse2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = byte_set_index_sse2(&self.set, false, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::TakeTill1)
+    }
+}
+
+impl_parser!(TakeTill1Sse2<M>, M: Mode);
+
+impl<M: Mode> TakeTill1Sse2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = byte_set_index_sse2_padded(&self.set, false, input);
+        padded_result(input, take_while1_result::<M, _>(input.as_bytes(), i, ErrorKind::TakeTill1))
+    }
+}
+
+impl_padded_parser!(TakeTill1Sse2<M>, M: Mode);
+
+/// same as [`take_till_sse2`], the prefix must not be empty
+#[inline(always)]
+pub fn take_till1_sse2_mode<M: Mode>(
+    chars: &[u8],
+) -> TakeTill1Sse2<M> {
+    TakeTill1Sse2 {
+        set: ByteSet::new(chars),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_till_avx2`]
+#[derive(Clone)]
+pub struct TakeTillAvx2<M = Streaming> {
+    set: ByteSet,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TakeTillAvx2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = byte_set_index_avx2(&self.set, false, input);
+        take_while0_result::<M, Error>(input, i)
+    }
+}
+
+impl_parser!(TakeTillAvx2<M>, M: Mode);
+
+impl<M: Mode> TakeTillAvx2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = byte_set_index_avx2_padded(&self.set, false, input);
+        padded_result(input, take_while0_result::<M, _>(input.as_bytes(), i))
+    }
+}
+
+impl_padded_parser!(TakeTillAvx2<M>, M: Mode);
+
+/// recognizes the longest prefix without bytes from `chars`, one vector
+/// compare per byte of the set, for sets up to 16 bytes
+#[inline(always)]
+pub fn take_till_avx2_mode<M: Mode>(
+    chars: &[u8],
+) -> TakeTillAvx2<M> {
+    TakeTillAvx2 {
+        set: ByteSet::new(chars),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_till1_avx2`]
+#[derive(Clone)]
+pub struct TakeTill1Avx2<M = Streaming> {
+    set: ByteSet,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TakeTill1Avx2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = byte_set_index_avx2(&self.set, false, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::TakeTill1)
+    }
+}
+
+impl_parser!(TakeTill1Avx2<M>, M: Mode);
+
+impl<M: Mode> TakeTill1Avx2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = byte_set_index_avx2_padded(&self.set, false, input);
+        padded_result(input, take_while1_result::<M, _>(input.as_bytes(), i, ErrorKind::TakeTill1))
+    }
+}
+
+impl_padded_parser!(TakeTill1Avx2<M>, M: Mode);
+
+/// same as [`take_till_avx2`], the prefix must not be empty
+#[inline(always)]
+pub fn take_till1_avx2_mode<M: Mode>(
+    chars: &[u8],
+) -> TakeTill1Avx2<M> {
+    TakeTill1Avx2 {
+        set: ByteSet::new(chars),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_till_shufti`]
+#[derive(Clone, Copy)]
+pub struct TakeTillShufti<'b, M = Streaming> {
+    set: &'b Shufti,
+    mode: PhantomData<M>,
+}
+
+impl<'b, M: Mode> TakeTillShufti<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = shufti_index(self.set, false, input);
+        take_while0_result::<M, Error>(input, i)
+    }
+}
+
+impl_parser!(TakeTillShufti<'b, M>, 'b, M: Mode);
+
+impl<'b, M: Mode> TakeTillShufti<'b, M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = shufti_index_padded(self.set, false, input);
+        padded_result(input, take_while0_result::<M, _>(input.as_bytes(), i))
+    }
+}
+
+impl_padded_parser!(TakeTillShufti<'b, M>, 'b, M: Mode);
+
+/// recognizes the longest prefix without bytes from `set`, see [make_shufti](crate::make_shufti)
+#[inline(always)]
+pub const fn take_till_shufti_mode<'b, M: Mode>(
+    set: &'b Shufti,
+) -> TakeTillShufti<'b, M> {
+    TakeTillShufti {
+        set,
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_till1_shufti`]
+#[derive(Clone, Copy)]
+pub struct TakeTill1Shufti<'b, M = Streaming> {
+    set: &'b Shufti,
+    mode: PhantomData<M>,
+}
+
+impl<'b, M: Mode> TakeTill1Shufti<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = shufti_index(self.set, false, input);
+        take_while1_result::<M, Error>(input, i, ErrorKind::TakeTill1)
+    }
+}
+
+impl_parser!(TakeTill1Shufti<'b, M>, 'b, M: Mode);
+
+impl<'b, M: Mode> TakeTill1Shufti<'b, M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = shufti_index_padded(self.set, false, input);
+        padded_result(input, take_while1_result::<M, _>(input.as_bytes(), i, ErrorKind::TakeTill1))
+    }
+}
+
+impl_padded_parser!(TakeTill1Shufti<'b, M>, 'b, M: Mode);
+
+/// recognizes the longest non empty prefix without bytes from `set`, see [make_shufti](crate::make_shufti)
+#[inline(always)]
+pub const fn take_till1_shufti_mode<'b, M: Mode>(
+    set: &'b Shufti,
+) -> TakeTill1Shufti<'b, M> {
+    TakeTill1Shufti {
+        set,
+        mode: PhantomData,
+    }
+}
+
+/// returns the first position `>= start` where `tag` occurs in `input`
+#[inline(always)]
+fn find_substring_scalar(tag: &[u8], input: &[u8], start: usize) -> Option<usize> {
+    if tag.is_empty() {
+        return Some(start);
+    }
+    if start + tag.len() > input.len() {
+        return None;
+    }
+
+    (start..=input.len() - tag.len())
+        .find(|&i| input[i] == tag[0] && &input[i..i + tag.len()] == tag)
+}
+
+/// uses SSE2 on x86_64, otherwise falls back to `find_substring_scalar`
+#[inline(always)]
+fn find_substring_sse2(tag: &[u8], input: &[u8], start: usize) -> Option<usize> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if !tag.is_empty() {
+            return unsafe { find_substring_sse2_filter::<false>(tag, input, start, input.len()) };
+        }
+    }
+
+    find_substring_scalar(tag, input, start)
+}
+
+/// same as `find_substring_sse2`, without a scalar tail
+#[inline(always)]
+fn find_substring_sse2_padded(tag: &[u8], input: PaddedInput, start: usize) -> Option<usize> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if !tag.is_empty() {
+            return unsafe { find_substring_sse2_filter::<true>(tag, input.with_padding(), start, input.len()) };
+        }
+    }
+
+    find_substring_scalar(tag, input.as_bytes(), start)
+}
+
+/// uses AVX2 if available, otherwise falls back to `find_substring_sse2`
+#[inline(always)]
+fn find_substring_avx2(tag: &[u8], input: &[u8], start: usize) -> Option<usize> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if !tag.is_empty() && crate::cpu::has_avx2() {
+            return unsafe { find_substring_avx2_filter::<false>(tag, input, start, input.len()) };
+        }
+    }
+
+    find_substring_sse2(tag, input, start)
+}
+
+/// same as `find_substring_avx2`, without a scalar tail
+#[inline(always)]
+fn find_substring_avx2_padded(tag: &[u8], input: PaddedInput, start: usize) -> Option<usize> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if !tag.is_empty() && crate::cpu::has_avx2() {
+            return unsafe { find_substring_avx2_filter::<true>(tag, input.with_padding(), start, input.len()) };
+        }
+    }
+
+    find_substring_sse2_padded(tag, input, start)
+}
+
+/// compares 16 positions at a time with the first and last bytes of the tag,
+/// then verifies the candidates
+///
+/// `tag` must not be empty, and `len` is the length of the input. With
+/// `PADDED`, `input` must contain 16 more bytes, and the candidates past
+/// the end of the input are masked
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn find_substring_sse2_filter<const PADDED: bool>(tag: &[u8], input: &[u8], start: usize, len: usize) -> Option<usize> {
+    use std::arch::x86_64::{
+        __m128i, _mm_and_si128, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8,
+        _mm_set1_epi8,
+    };
+
+    let last_offset = tag.len() - 1;
+    let first = _mm_set1_epi8(tag[0] as i8);
+    let last = _mm_set1_epi8(tag[last_offset] as i8);
+
+    debug_assert!(input.len() >= if PADDED { len + 16 } else { len });
+
+    let mut i = start;
+    while if PADDED { i + last_offset < len } else { i + last_offset + 16 <= len } {
+        let block_first = _mm_loadu_si128(input.as_ptr().add(i) as *const __m128i);
+        let block_last = _mm_loadu_si128(input.as_ptr().add(i + last_offset) as *const __m128i);
+
+        let mut mask = _mm_movemask_epi8(_mm_and_si128(
+            _mm_cmpeq_epi8(first, block_first),
+            _mm_cmpeq_epi8(last, block_last),
+        )) as u32;
+        if PADDED {
+            // the tag would end past the end of the input
+            mask &= !past_end_mask(len - last_offset - i);
+        }
+
+        while mask != 0 {
+            let candidate = i + mask.trailing_zeros() as usize;
+            if input[candidate + 1..candidate + tag.len()] == tag[1..] {
+                return Some(candidate);
+            }
+            mask &= mask - 1;
+        }
+
+        i += 16;
+    }
+
+    find_substring_scalar(tag, &input[..len], i)
+}
+
+/// compares 32 positions at a time with the first and last bytes of the tag,
+/// then verifies the candidates, see `find_substring_sse2_filter`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn find_substring_avx2_filter<const PADDED: bool>(tag: &[u8], input: &[u8], start: usize, len: usize) -> Option<usize> {
+    use std::arch::x86_64::{
+        __m256i, _mm256_and_si256, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_movemask_epi8,
+        _mm256_set1_epi8,
+    };
+
+    let last_offset = tag.len() - 1;
+    let first = _mm256_set1_epi8(tag[0] as i8);
+    let last = _mm256_set1_epi8(tag[last_offset] as i8);
+
+    debug_assert!(input.len() >= if PADDED { len + 32 } else { len });
+
+    let mut i = start;
+    while if PADDED { i + last_offset < len } else { i + last_offset + 32 <= len } {
+        let block_first = _mm256_loadu_si256(input.as_ptr().add(i) as *const __m256i);
+        let block_last =
+            _mm256_loadu_si256(input.as_ptr().add(i + last_offset) as *const __m256i);
+
+        let mut mask = _mm256_movemask_epi8(_mm256_and_si256(
+            _mm256_cmpeq_epi8(first, block_first),
+            _mm256_cmpeq_epi8(last, block_last),
+        )) as u32;
+        if PADDED {
+            mask &= !past_end_mask(len - last_offset - i);
+        }
+
+        while mask != 0 {
+            let candidate = i + mask.trailing_zeros() as usize;
+            if input[candidate + 1..candidate + tag.len()] == tag[1..] {
+                return Some(candidate);
+            }
+            mask &= mask - 1;
+        }
+
+        i += 32;
+    }
+
+    find_substring_sse2_filter::<PADDED>(tag, input, i, len)
+}
+
+#[inline(always)]
+fn take_until_result<'a, M: Mode, Error: ParseError<&'a [u8]>>(
+    input: &'a [u8],
+    index: Option<usize>,
+) -> IResult<&'a [u8], &'a [u8], Error> {
+    match index {
+        Some(i) => Ok((&input[i..], &input[..i])),
+        None if M::STREAMING => Err(Err::Incomplete(Needed::Unknown)),
+        None => Err(Err::Error(Error::from_error_kind(input, ErrorKind::TakeUntil))),
+    }
+}
+
+/// the offset from which a match is still possible after a search for a
+/// tag of `tag_len` bytes in `input`, 0 if the search is over
+#[inline(always)]
+fn resume_offset<M: Mode>(tag_len: usize, input: &[u8], index: Option<usize>) -> usize {
+    if index.is_none() && M::STREAMING {
+        // the last `tag.len() - 1` bytes could be the beginning of a match
+        (input.len() + 1).saturating_sub(tag_len)
+    } else {
+        0
+    }
+}
+
+/// where the `take_until` parsers resume their search after `Err::Incomplete`
+///
+/// the offset is only used if the next input starts at the same address, and
+/// is not shorter: the caller appended bytes to the same buffer. Any other
+/// input is searched from the start.
+#[derive(Clone, Copy, Debug, Default)]
+struct Resume {
+    address: usize,
+    len: usize,
+    offset: usize,
+}
+
+impl Resume {
+    const fn new() -> Self {
+        Resume {
+            address: 0,
+            len: 0,
+            offset: 0,
+        }
+    }
+
+    /// the offset at which the search in `input` starts
+    #[inline(always)]
+    fn start(&self, input: &[u8]) -> usize {
+        if input.as_ptr() as usize == self.address && input.len() >= self.len {
+            self.offset
+        } else {
+            0
+        }
+    }
+
+    /// keeps the offset to resume from after a search in `input`
+    #[inline(always)]
+    fn update<M: Mode>(&mut self, tag_len: usize, input: &[u8], index: Option<usize>) {
+        *self = Resume {
+            address: input.as_ptr() as usize,
+            len: input.len(),
+            offset: resume_offset::<M>(tag_len, input, index),
+        };
+    }
+}
+
+/// parser built by [`take_until_sse2`]
+///
+/// in streaming mode, after `Err::Incomplete`, the next call on the same
+/// buffer with more bytes appended resumes the search where this one stopped,
+/// so `parse` takes `&mut self`. The bytes already scanned must not change
+/// in between, a buffer that was consumed or moved is searched from the start
+#[derive(Clone, Copy)]
+pub struct TakeUntilSse2<'b, M = Streaming> {
+    tag: &'b [u8],
+    resume: Resume,
+    mode: PhantomData<M>,
+}
+
+impl<'b, M: Mode> TakeUntilSse2<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &mut self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let index = find_substring_sse2(self.tag, input, self.resume.start(input));
+        self.resume.update::<M>(self.tag.len(), input, index);
+        take_until_result::<M, Error>(input, index)
+    }
+}
+
+impl_parser!(TakeUntilSse2<'b, M>, 'b, M: Mode);
+
+impl<'b, M: Mode> TakeUntilSse2<'b, M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &mut self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let index = find_substring_sse2_padded(self.tag, input, self.resume.start(input.as_bytes()));
+        self.resume.update::<M>(self.tag.len(), input.as_bytes(), index);
+        padded_result(input, take_until_result::<M, _>(input.as_bytes(), index))
+    }
+}
+
+impl_padded_parser!(TakeUntilSse2<'b, M>, 'b, M: Mode);
+
+/// returns the input up to the first occurrence of `tag`
+#[inline(always)]
+pub const fn take_until_sse2_mode<'b, M: Mode>(
+    tag: &'b [u8],
+) -> TakeUntilSse2<'b, M> {
+    TakeUntilSse2 {
+        tag,
+        resume: Resume::new(),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_until_avx2`]
+///
+/// resumes its search like [`TakeUntilSse2`]
+#[derive(Clone, Copy)]
+pub struct TakeUntilAvx2<'b, M = Streaming> {
+    tag: &'b [u8],
+    resume: Resume,
+    mode: PhantomData<M>,
+}
+
+impl<'b, M: Mode> TakeUntilAvx2<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &mut self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let index = find_substring_avx2(self.tag, input, self.resume.start(input));
+        self.resume.update::<M>(self.tag.len(), input, index);
+        take_until_result::<M, Error>(input, index)
+    }
+}
+
+impl_parser!(TakeUntilAvx2<'b, M>, 'b, M: Mode);
+
+impl<'b, M: Mode> TakeUntilAvx2<'b, M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &mut self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let index = find_substring_avx2_padded(self.tag, input, self.resume.start(input.as_bytes()));
+        self.resume.update::<M>(self.tag.len(), input.as_bytes(), index);
+        padded_result(input, take_until_result::<M, _>(input.as_bytes(), index))
+    }
+}
+
+impl_padded_parser!(TakeUntilAvx2<'b, M>, 'b, M: Mode);
+
+/// returns the input up to the first occurrence of `tag`
+#[inline(always)]
+pub const fn take_until_avx2_mode<'b, M: Mode>(
+    tag: &'b [u8],
+) -> TakeUntilAvx2<'b, M> {
+    TakeUntilAvx2 {
+        tag,
+        resume: Resume::new(),
+        mode: PhantomData,
+    }
+}
+
+/// parser built by [`take_until_resumable`]
+///
+/// `parse` and `parse_padded` keep the offset to resume from, like
+/// [`TakeUntilAvx2`], while [`parse_from`](TakeUntilResumable::parse_from)
+/// and [`parse_padded_from`](TakeUntilResumable::parse_padded_from) return
+/// it to the caller
+#[derive(Clone, Copy)]
+pub struct TakeUntilResumable<'b, M = Streaming> {
+    inner: TakeUntilAvx2<'b, M>,
+}
+
+impl<'b, M: Mode> TakeUntilResumable<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &mut self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        self.inner.parse(input)
+    }
+
+    /// starts the search at `start`, and returns with the result the offset
+    /// at which the next call can start
+    ///
+    /// `start` must be 0, or the offset returned with `Err::Incomplete` by
+    /// the previous call on the same data, before more bytes were appended.
+    /// The returned offset is 0 for any other result.
+    #[inline(always)]
+    pub fn parse_from<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+        start: usize,
+    ) -> (IResult<&'a [u8], &'a [u8], Error>, usize) {
+        let tag = self.inner.tag;
+        let start = std::cmp::min(start, input.len());
+        let index = find_substring_avx2(tag, input, start);
+        (take_until_result::<M, Error>(input, index), resume_offset::<M>(tag.len(), input, index))
+    }
+
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &mut self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        self.inner.parse_padded(input)
+    }
+
+    /// same as `parse_from`, the end of the input is handled with the
+    /// padding instead of a scalar loop
+    #[inline(always)]
+    pub fn parse_padded_from<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+        start: usize,
+    ) -> (IResult<PaddedInput<'a>, &'a [u8], Error>, usize) {
+        let tag = self.inner.tag;
+        let start = std::cmp::min(start, input.len());
+        let index = find_substring_avx2_padded(tag, input, start);
+        let res = padded_result(input, take_until_result::<M, _>(input.as_bytes(), index));
+        (res, resume_offset::<M>(tag.len(), input.as_bytes(), index))
+    }
+}
+
+impl_parser!(TakeUntilResumable<'b, M>, 'b, M: Mode);
+impl_padded_parser!(TakeUntilResumable<'b, M>, 'b, M: Mode);
+
+/// like `take_until_avx2`, but [`TakeUntilResumable::parse_from`] returns
+/// with `Err::Incomplete` the position from which a match is still
+/// possible. Passing it to the next call, with the same data followed by
+/// more bytes, resumes the search from there instead of scanning the whole
+/// buffer again.
+///
+/// The caller keeps that offset, so it knows when it is no longer valid:
+/// when the data is consumed, moved or modified, the search restarts at 0.
+#[inline(always)]
+pub const fn take_until_resumable_mode<'b, M: Mode>(
+    tag: &'b [u8],
+) -> TakeUntilResumable<'b, M> {
+    TakeUntilResumable {
+        inner: take_until_avx2_mode(tag),
+    }
+}
+
+/// returns the length of the common prefix of `tag` and `input`
+#[inline(always)]
+fn tag_unrolled_index(tag: &[u8], input: &[u8]) -> usize {
+    let mut i = 0usize;
+    let len = std::cmp::min(tag.len(), input.len());
+    let mut found = false;
+
+    loop {
+        if len - i < 8 {
+            break;
+        }
+
+        if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
+            found = true;
+            break;
+        }
+        i += 1;
+
+        if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
+            found = true;
+            break;
+        }
+        i += 1;
+    }
+
+    if !found {
+        while i < len && unsafe { *tag.get_unchecked(i) == *input.get_unchecked(i) } {
+            i += 1;
+        }
+    }
+
+    i
+}
+
+#[inline(always)]
+fn tag_result<'a, M: Mode, Error: ParseError<&'a [u8]>>(
+    tag: &[u8],
+    input: &'a [u8],
+    i: usize,
+) -> IResult<&'a [u8], &'a [u8], Error> {
+    if i == tag.len() {
+        let (prefix, suffix) = input.split_at(i);
+        Ok((suffix, prefix))
+    } else if i == input.len() && M::STREAMING {
+        Err(Err::Incomplete(Needed::new(tag.len() - i)))
+    } else {
+        Err(Err::Error(Error::from_error_kind(input, ErrorKind::Tag)))
+    }
+}
+
+/// parser built by [`tag_unrolled`]
+#[derive(Clone, Copy)]
+pub struct TagUnrolled<'b, M = Streaming> {
+    tag: &'b [u8],
+    mode: PhantomData<M>,
+}
+
+impl<'b, M: Mode> TagUnrolled<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = tag_unrolled_index(self.tag, input);
+        tag_result::<M, Error>(self.tag, input, i)
+    }
+}
+
+impl_parser!(TagUnrolled<'b, M>, 'b, M: Mode);
+
+#[inline(always)]
+pub const fn tag_unrolled_mode<'b, M: Mode>(
+    tag: &'b [u8],
+) -> TagUnrolled<'b, M> {
+    TagUnrolled {
+        tag,
+        mode: PhantomData,
+    }
+}
+
+/// uses SSE4.2 if available, otherwise falls back to `tag_unrolled_index`
+#[inline(always)]
+fn tag_sse2_index(tag: &TagMatcher, input: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_sse42() {
+            return unsafe { tag_index_sse42::<false>(tag, input, input.len()) };
+        }
+    }
+
+    tag_unrolled_index(tag.as_bytes(), input)
+}
+
+/// same as `tag_sse2_index`, the end of the input is loaded directly
+#[inline(always)]
+fn tag_sse2_index_padded(tag: &TagMatcher, input: PaddedInput) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_sse42() {
+            return unsafe { tag_index_sse42::<true>(tag, input.with_padding(), input.len()) };
+        }
+    }
+
+    tag_unrolled_index(tag.as_bytes(), input.as_bytes())
+}
+
+/// compares the tag 16 bytes at a time. The tag chunks are already padded,
+/// and the end of the input is copied to an aligned buffer, so no load goes
+/// past the end of the input
+///
+/// `len` is the length of the input. With `PADDED`, `input` must contain 16
+/// more bytes, so the end of the input can be loaded without a copy
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2")]
+unsafe fn tag_index_sse42<const PADDED: bool>(tag: &TagMatcher, input: &[u8], len: usize) -> usize {
+    use std::arch::x86_64::{
+        _mm_cmpestri, _mm_load_si128, _mm_loadu_si128, _SIDD_CMP_EQUAL_EACH,
+        _SIDD_LEAST_SIGNIFICANT, _SIDD_NEGATIVE_POLARITY, _SIDD_UBYTE_OPS,
+    };
+
+    debug_assert!(input.len() >= if PADDED { len + 16 } else { len });
+
+    let mut index = 0;
+    for chunk in tag.chunks() {
+        let current_tag_len = std::cmp::min(tag.len() - index, 16);
+        let current_slice_len = std::cmp::min(len - index, 16);
+
+        let mut tail = Aligned16([0u8; 16]);
+        let slice = if PADDED || current_slice_len == 16 {
+            _mm_loadu_si128(input.as_ptr().add(index) as *const _)
+        } else {
+            tail.0[..current_slice_len].copy_from_slice(&input[index..len]);
+            _mm_load_si128(tail.0.as_ptr() as *const _)
+        };
+
+        // returns the index of the first difference, or 16 if the first
+        // `current_tag_len` bytes are the same and the lengths are equal
+        let idx = _mm_cmpestri(
+            _mm_load_si128(chunk.0.as_ptr() as *const _),
+            current_tag_len as i32,
+            slice,
+            current_slice_len as i32,
+            _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_EQUAL_EACH | _SIDD_UBYTE_OPS | _SIDD_NEGATIVE_POLARITY,
+        ) as usize;
+
+        let common = std::cmp::min(idx, std::cmp::min(current_tag_len, current_slice_len));
+        index += common;
+        if common < 16 {
+            break;
+        }
+    }
+
+    index
+}
+
+/// parser built by [`tag_sse2`]
+#[derive(Clone)]
+pub struct TagSse2<M = Streaming> {
+    tag: TagMatcher,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> TagSse2<M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = tag_sse2_index(&self.tag, input);
+        tag_result::<M, Error>(self.tag.as_bytes(), input, i)
+    }
+}
+
+impl_parser!(TagSse2<M>, M: Mode);
+
+impl<M: Mode> TagSse2<M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = tag_sse2_index_padded(&self.tag, input);
+        padded_result(input, tag_result::<M, _>(self.tag.as_bytes(), input.as_bytes(), i))
+    }
+}
+
+impl_padded_parser!(TagSse2<M>, M: Mode);
+
+/// copies `tag` to a [TagMatcher], so it is not reloaded on each call
+#[inline(always)]
+pub fn tag_sse2_mode<M: Mode>(
+    tag: &[u8],
+) -> TagSse2<M> {
+    TagSse2 {
+        tag: TagMatcher::new(tag),
+        mode: PhantomData,
+    }
+}
+
+/// returns the length of the common prefix of `tag` and `input`, ignoring ASCII case
+#[inline(always)]
+fn tag_no_case_unrolled_index(tag: &[u8], input: &[u8]) -> usize {
+    let mut i = 0usize;
+    let len = std::cmp::min(tag.len(), input.len());
+
+    while len - i >= 8 {
+        let mut tag_word = [0u8; 8];
+        let mut input_word = [0u8; 8];
+        tag_word.copy_from_slice(&tag[i..i + 8]);
+        input_word.copy_from_slice(&input[i..i + 8]);
+
+        let diff = to_ascii_lowercase_swar(u64::from_le_bytes(tag_word))
+            ^ to_ascii_lowercase_swar(u64::from_le_bytes(input_word));
+        if diff != 0 {
+            return i + (diff.trailing_zeros() / 8) as usize;
+        }
+        i += 8;
+    }
+
+    while i < len && to_ascii_lowercase_scalar(tag[i]) == to_ascii_lowercase_scalar(input[i]) {
+        i += 1;
+    }
+
+    i
+}
+
+/// uses SSE2 on x86_64, otherwise falls back to `tag_no_case_unrolled_index`
+#[inline(always)]
+fn tag_no_case_sse2_index(tag: &[u8], input: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        unsafe { tag_no_case_index_sse2::<false>(tag, input, input.len()) }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        tag_no_case_unrolled_index(tag, input)
+    }
+}
+
+/// same as `tag_no_case_sse2_index`, without a scalar tail
+#[inline(always)]
+fn tag_no_case_sse2_index_padded(tag: &[u8], input: PaddedInput) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        unsafe { tag_no_case_index_sse2::<true>(tag, input.with_padding(), input.len()) }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        tag_no_case_unrolled_index(tag, input.as_bytes())
+    }
+}
+
+/// uses AVX2 if available, otherwise falls back to `tag_no_case_sse2_index`
+#[inline(always)]
+fn tag_no_case_avx2_index(tag: &[u8], input: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_avx2() {
+            return unsafe { tag_no_case_index_avx2::<false>(tag, input, input.len()) };
+        }
+    }
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+    tag_no_case_sse2_index(tag, input)
+}
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+/// same as `tag_no_case_avx2_index`, without a scalar tail
+#[inline(always)]
+fn tag_no_case_avx2_index_padded(tag: &[u8], input: PaddedInput) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::has_avx2() {
+            return unsafe { tag_no_case_index_avx2::<true>(tag, input.with_padding(), input.len()) };
+        }
+    }
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+    tag_no_case_sse2_index_padded(tag, input)
+}
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
+/// `len` is the length of the input. With `PADDED`, `input` must contain 16
+/// more bytes. The tag is not padded, so the end of the tag is copied to a
+/// buffer, and the bytes past the end of the tag or the input are masked
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn tag_no_case_index_sse2<const PADDED: bool>(tag: &[u8], input: &[u8], len: usize) -> usize {
+    use std::arch::x86_64::{__m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8};
+
+    debug_assert!(input.len() >= if PADDED { len + 16 } else { len });
+
+    let input_len = len;
+    let len = std::cmp::min(tag.len(), len);
+    let mut i = 0usize;
+    while i + 16 <= len {
+        let t = _mm_loadu_si128(tag.as_ptr().add(i) as *const __m128i);
+        let v = _mm_loadu_si128(input.as_ptr().add(i) as *const __m128i);
+
+        let mask = _mm_movemask_epi8(_mm_cmpeq_epi8(to_ascii_lowercase_sse2(t), to_ascii_lowercase_sse2(v))) as u32;
+        if mask != 0xFFFF {
+            return i + (!mask).trailing_zeros() as usize;
+        }
+        i += 16;
+    }
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+    if PADDED && i < len {
+        let mut tail = [0u8; 16];
+        tail[..len - i].copy_from_slice(&tag[i..len]);
+        let t = _mm_loadu_si128(tail.as_ptr() as *const __m128i);
+        let v = _mm_loadu_si128(input.as_ptr().add(i) as *const __m128i);
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+        let mask = _mm_movemask_epi8(_mm_cmpeq_epi8(to_ascii_lowercase_sse2(t), to_ascii_lowercase_sse2(v))) as u32;
+        let diff = !mask & !past_end_mask(len - i);
+        return if diff != 0 { i + diff.trailing_zeros() as usize } else { len };
+    }
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+    i + tag_no_case_unrolled_index(&tag[i..], &input[i..input_len])
+}
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+/// see `tag_no_case_index_sse2`, the end is handled by the SSE2 version
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn tag_no_case_index_avx2<const PADDED: bool>(tag: &[u8], input: &[u8], len: usize) -> usize {
+    use std::arch::x86_64::{
+        __m256i, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_movemask_epi8,
+    };
+
+    let input_len = len;
+    let len = std::cmp::min(tag.len(), len);
+    let mut i = 0usize;
+    while i + 32 <= len {
+        let t = _mm256_loadu_si256(tag.as_ptr().add(i) as *const __m256i);
+        let v = _mm256_loadu_si256(input.as_ptr().add(i) as *const __m256i);
+
+        let mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(to_ascii_lowercase_avx2(t), to_ascii_lowercase_avx2(v))) as u32;
+        if mask != 0xFFFF_FFFF {
+            return i + (!mask).trailing_zeros() as usize;
         }
+        i += 32;
+    }
 
-        if !found {
-            loop {
-                if !cond(unsafe { *input.get_unchecked(i) }) {
-                    break;
-                }
-                i = i + 1;
-                if i == len {
-                    break;
-                }
-            }
-        }
+    i + tag_no_case_index_sse2::<PADDED>(&tag[i..], &input[i..], input_len - i)
+}
 
-        if i == len {
-            Err(Err::Incomplete(Needed::Unknown))
-        } else {
-            let (prefix, suffix) = input.split_at(i);
-            Ok((suffix, prefix))
-        }
+/// parser built by [`tag_no_case_unrolled`]
+#[derive(Clone, Copy)]
+pub struct TagNoCaseUnrolled<'b, M = Streaming> {
+    tag: &'b [u8],
+    mode: PhantomData<M>,
+}
+
+impl<'b, M: Mode> TagNoCaseUnrolled<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = tag_no_case_unrolled_index(self.tag, input);
+        tag_result::<M, Error>(self.tag, input, i)
     }
 }
 
-pub fn take_while1_unrolled<'a, F, Error: ParseError<&'a [u8]>>(
-    cond: F,
-) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], &'a [u8], Error>
-where
-    F: Fn(u8) -> bool,
-{
-    move |input: &'a [u8]| {
-        let mut i = 0usize;
-        let len = input.len();
-        let mut found = false;
+impl_parser!(TagNoCaseUnrolled<'b, M>, 'b, M: Mode);
 
-        loop {
-            if len - i < 8 {
-                break;
-            }
+/// recognizes `tag`, ignoring the case of ASCII letters
+#[inline(always)]
+pub const fn tag_no_case_unrolled_mode<'b, M: Mode>(
+    tag: &'b [u8],
+) -> TagNoCaseUnrolled<'b, M> {
+    TagNoCaseUnrolled {
+        tag,
+        mode: PhantomData,
+    }
+}
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+/// parser built by [`tag_no_case_sse2`]
+#[derive(Clone, Copy)]
+pub struct TagNoCaseSse2<'b, M = Streaming> {
+    tag: &'b [u8],
+    mode: PhantomData<M>,
+}
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+impl<'b, M: Mode> TagNoCaseSse2<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = tag_no_case_sse2_index(self.tag, input);
+        tag_result::<M, Error>(self.tag, input, i)
+    }
+}
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+impl_parser!(TagNoCaseSse2<'b, M>, 'b, M: Mode);
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
+impl<'b, M: Mode> TagNoCaseSse2<'b, M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = tag_no_case_sse2_index_padded(self.tag, input);
+        padded_result(input, tag_result::<M, _>(self.tag, input.as_bytes(), i))
+    }
+}
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+impl_padded_parser!(TagNoCaseSse2<'b, M>, 'b, M: Mode);
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+/// recognizes `tag`, ignoring the case of ASCII letters
+#[inline(always)]
+pub const fn tag_no_case_sse2_mode<'b, M: Mode>(
+    tag: &'b [u8],
+) -> TagNoCaseSse2<'b, M> {
+    TagNoCaseSse2 {
+        tag,
+        mode: PhantomData,
+    }
+}
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
+/// parser built by [`tag_no_case_avx2`]
+#[derive(Clone, Copy)]
+pub struct TagNoCaseAvx2<'b, M = Streaming> {
+    tag: &'b [u8],
+    mode: PhantomData<M>,
+}
 
-            if !cond(unsafe { *input.get_unchecked(i) }) {
-                found = true;
-                break;
-            }
-            i = i + 1;
-        }
+impl<'b, M: Mode> TagNoCaseAvx2<'b, M> {
+    #[inline(always)]
+    pub fn parse<'a, Error: ParseError<&'a [u8]>>(
+        &self,
+        input: &'a [u8],
+    ) -> IResult<&'a [u8], &'a [u8], Error> {
+        let i = tag_no_case_avx2_index(self.tag, input);
+        tag_result::<M, Error>(self.tag, input, i)
+    }
+}
 
-        if !found {
-            loop {
-                if !cond(unsafe { *input.get_unchecked(i) }) {
-                    break;
-                }
-                i = i + 1;
-                if i == len {
-                    break;
-                }
-            }
-        }
+impl_parser!(TagNoCaseAvx2<'b, M>, 'b, M: Mode);
 
-        if i == 0 {
-            Err(Err::Error(Error::from_error_kind(
-                input,
-                ErrorKind::TakeWhile1,
-            )))
-        } else if i == len {
-            Err(Err::Incomplete(Needed::Unknown))
-        } else {
-            let (prefix, suffix) = input.split_at(i);
-            Ok((suffix, prefix))
-        }
+impl<'b, M: Mode> TagNoCaseAvx2<'b, M> {
+    #[inline(always)]
+    pub fn parse_padded<'a, Error: ParseError<PaddedInput<'a>>>(
+        &self,
+        input: PaddedInput<'a>,
+    ) -> IResult<PaddedInput<'a>, &'a [u8], Error> {
+        let i = tag_no_case_avx2_index_padded(self.tag, input);
+        padded_result(input, tag_result::<M, _>(self.tag, input.as_bytes(), i))
     }
 }
 
-#[inline(always)]
-#[cfg(feature = "sse2")]
-pub fn take_while0_sse2<'a, 'b: 'a, F>(
-    predicate: F,
-    ranges: &'b [u8],
-) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], &'a [u8], ()>
-where
-    F: Fn(u8) -> bool,
-{
-    move |input: &'a [u8]| {
-        use std::arch::x86_64::{
-            _mm_cmpestri, _mm_loadu_si128, _SIDD_CMP_RANGES, _SIDD_LEAST_SIGNIFICANT,
-            _SIDD_UBYTE_OPS,
-        };
+impl_padded_parser!(TagNoCaseAvx2<'b, M>, 'b, M: Mode);
 
-        let start = input.as_ptr() as usize;
-        let mut i = input.as_ptr() as usize;
-        let mut left = input.len();
-        let mut found = false;
-
-        if left >= 16 {
-            let ranges16 = unsafe { _mm_loadu_si128(ranges.as_ptr() as *const _) };
-            let ranges_len = ranges.len() as i32;
-            loop {
-                let sl = unsafe { _mm_loadu_si128(i as *const _) };
-
-                let idx = unsafe {
-                    _mm_cmpestri(
-                        ranges16,
-                        ranges_len,
-                        sl,
-                        16,
-                        _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_RANGES | _SIDD_UBYTE_OPS,
-                    )
-                };
-
-                if idx != 16 {
-                    i += idx as usize;
-                    found = true;
-                    break;
-                }
+/// recognizes `tag`, ignoring the case of ASCII letters
+#[inline(always)]
+pub const fn tag_no_case_avx2_mode<'b, M: Mode>(
+    tag: &'b [u8],
+) -> TagNoCaseAvx2<'b, M> {
+    TagNoCaseAvx2 {
+        tag,
+        mode: PhantomData,
+    }
+}
 
-                i += 16;
-                left -= 16;
+// the functions at the root of the module keep nom's streaming behaviour,
+// the `_mode` versions take the mode as a type parameter
+pub use self::streaming::*;
+
+mode_modules! {
+    const fn take_while0_unrolled[F: Fn(u8) -> bool](cond: F) -> TakeWhile0Unrolled[F] = take_while0_unrolled_mode;
+    const fn take_while1_unrolled[F: Fn(u8) -> bool](cond: F) -> TakeWhile1Unrolled[F] = take_while1_unrolled_mode;
+    const fn take_while_m_n_unrolled[F: Fn(u8) -> bool](m: usize, n: usize, cond: F) -> TakeWhileMNUnrolled[F] = take_while_m_n_unrolled_mode;
+    const fn take_while0_sse2[](ranges: Ranges) -> TakeWhile0Sse2[] = take_while0_sse2_mode;
+    const fn take_while1_sse2[](ranges: Ranges) -> TakeWhile1Sse2[] = take_while1_sse2_mode;
+    const fn take_while_m_n_sse2[](m: usize, n: usize, ranges: Ranges) -> TakeWhileMNSse2[] = take_while_m_n_sse2_mode;
+    const fn take_while0_avx2[](ranges: Ranges) -> TakeWhile0Avx2[] = take_while0_avx2_mode;
+    const fn take_while1_avx2[](ranges: Ranges) -> TakeWhile1Avx2[] = take_while1_avx2_mode;
+    const fn take_while_m_n_avx2[](m: usize, n: usize, ranges: Ranges) -> TakeWhileMNAvx2[] = take_while_m_n_avx2_mode;
+    const fn take_while0_shufti['b](set: &'b Shufti) -> TakeWhile0Shufti['b] = take_while0_shufti_mode;
+    const fn take_while1_shufti['b](set: &'b Shufti) -> TakeWhile1Shufti['b] = take_while1_shufti_mode;
+    const fn take_while_m_n_shufti['b](m: usize, n: usize, set: &'b Shufti) -> TakeWhileMNShufti['b] = take_while_m_n_shufti_mode;
+    const fn take_till_unrolled[F: Fn(u8) -> bool](cond: F) -> TakeTillUnrolled[F] = take_till_unrolled_mode;
+    const fn take_till1_unrolled[F: Fn(u8) -> bool](cond: F) -> TakeTill1Unrolled[F] = take_till1_unrolled_mode;
+    fn is_a_unrolled[](chars: &[u8]) -> IsAUnrolled[] = is_a_unrolled_mode;
+    fn is_not_unrolled[](chars: &[u8]) -> IsNotUnrolled[] = is_not_unrolled_mode;
+    fn is_a_sse2[](chars: &[u8]) -> IsASse2[] = is_a_sse2_mode;
+    fn is_not_sse2[](chars: &[u8]) -> IsNotSse2[] = is_not_sse2_mode;
+    fn is_a_avx2[](chars: &[u8]) -> IsAAvx2[] = is_a_avx2_mode;
+    fn is_not_avx2[](chars: &[u8]) -> IsNotAvx2[] = is_not_avx2_mode;
+    fn take_till_sse2[](chars: &[u8]) -> TakeTillSse2[] = take_till_sse2_mode;
+    fn take_till1_sse2[](chars: &[u8]) -> TakeTill1Sse2[] = take_till1_sse2_mode;
+    fn take_till_avx2[](chars: &[u8]) -> TakeTillAvx2[] = take_till_avx2_mode;
+    fn take_till1_avx2[](chars: &[u8]) -> TakeTill1Avx2[] = take_till1_avx2_mode;
+    const fn take_till_shufti['b](set: &'b Shufti) -> TakeTillShufti['b] = take_till_shufti_mode;
+    const fn take_till1_shufti['b](set: &'b Shufti) -> TakeTill1Shufti['b] = take_till1_shufti_mode;
+    const fn take_until_sse2['b](tag: &'b [u8]) -> TakeUntilSse2['b] = take_until_sse2_mode;
+    const fn take_until_avx2['b](tag: &'b [u8]) -> TakeUntilAvx2['b] = take_until_avx2_mode;
+    const fn take_until_resumable['b](tag: &'b [u8]) -> TakeUntilResumable['b] = take_until_resumable_mode;
+    const fn tag_unrolled['b](tag: &'b [u8]) -> TagUnrolled['b] = tag_unrolled_mode;
+    fn tag_sse2[](tag: &[u8]) -> TagSse2[] = tag_sse2_mode;
+    const fn tag_no_case_unrolled['b](tag: &'b [u8]) -> TagNoCaseUnrolled['b] = tag_no_case_unrolled_mode;
+    const fn tag_no_case_sse2['b](tag: &'b [u8]) -> TagNoCaseSse2['b] = tag_no_case_sse2_mode;
+    const fn tag_no_case_avx2['b](tag: &'b [u8]) -> TagNoCaseAvx2['b] = tag_no_case_avx2_mode;
+}
 
-                if left < 16 {
-                    break;
-                }
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::Error;
 
-        let mut i = i - start;
-        if !found {
-            loop {
-                if !predicate(unsafe { *input.get_unchecked(i) }) {
-                    break;
-                }
-                i = i + 1;
-                if i == input.len() {
-                    break;
-                }
-            }
-        }
+    const fn is_a(c: u8) -> bool {
+        c == b'a'
+    }
 
-        if i == input.len() {
-            Err(Err::Incomplete(Needed::Unknown))
-        } else {
-            let (prefix, suffix) = input.split_at(i);
-            Ok((suffix, prefix))
-        }
+    #[test]
+    fn take_while_unrolled_mode_test() {
+        let input = &b"aaaaaaaaaaaaaaaaaaaa"[..];
+        let input_b = &b"aaaaaaaaaaaaaaaaaaab"[..];
+
+        let res: IResult<_, _, Error<&[u8]>> = streaming::take_while0_unrolled(is_a).parse(input);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(1))));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while0_unrolled(is_a).parse(input);
+        assert_eq!(res, Ok((&b""[..], input)));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while0_unrolled(is_a).parse(&b""[..]);
+        assert_eq!(res, Ok((&b""[..], &b""[..])));
+
+        let res: IResult<_, _, Error<&[u8]>> = streaming::take_while1_unrolled(is_a).parse(input);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(1))));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while1_unrolled(is_a).parse(input);
+        assert_eq!(res, Ok((&b""[..], input)));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while1_unrolled(is_a).parse(&b""[..]);
+        assert_eq!(res, Err(Err::Error(Error::new(&b""[..], ErrorKind::TakeWhile1))));
+
+        let res: IResult<_, _, Error<&[u8]>> = streaming::take_while1_unrolled(is_a).parse(input_b);
+        assert_eq!(res, Ok((&b"b"[..], &input_b[..19])));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while1_unrolled(is_a).parse(input_b);
+        assert_eq!(res, Ok((&b"b"[..], &input_b[..19])));
+
+        // the root functions are the streaming ones
+        let res: IResult<_, _, Error<&[u8]>> = take_while1_unrolled(is_a).parse(input);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(1))));
+        let res: IResult<_, _, Error<&[u8]>> = take_while1_unrolled_mode::<crate::mode::Complete, _>(is_a).parse(input);
+        assert_eq!(res, Ok((&b""[..], input)));
     }
-}
 
-#[inline(always)]
-#[cfg(feature = "sse2")]
-pub fn take_while1_sse2<'a, 'b: 'a, F, Error>(
-    predicate: F,
-    ranges: &'b [u8],
-) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], &'a [u8], Error>
-where
-    Error: ParseError<&'a [u8]>,
-    F: Fn(u8) -> bool,
-{
-    move |input: &'a [u8]| {
-        use std::arch::x86_64::{
-            _mm_cmpestri, _mm_loadu_si128, _SIDD_CMP_RANGES, _SIDD_LEAST_SIGNIFICANT,
-            _SIDD_UBYTE_OPS,
-        };
+    #[test]
+    fn tag_unrolled_mode_test() {
+        let tag = &b"ABCDEFGHIJ"[..];
 
-        let start = input.as_ptr() as usize;
-        let mut i = input.as_ptr() as usize;
-        let mut left = input.len();
-        let mut found = false;
-
-        if left >= 16 {
-            let ranges16 = unsafe { _mm_loadu_si128(ranges.as_ptr() as *const _) };
-            let ranges_len = ranges.len() as i32;
-            loop {
-                let sl = unsafe { _mm_loadu_si128(i as *const _) };
-
-                let idx = unsafe {
-                    _mm_cmpestri(
-                        ranges16,
-                        ranges_len,
-                        sl,
-                        16,
-                        _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_RANGES | _SIDD_UBYTE_OPS,
-                    )
-                };
-
-                if idx != 16 {
-                    i += idx as usize;
-                    found = true;
-                    break;
-                }
+        let res: IResult<_, _, Error<&[u8]>> = streaming::tag_unrolled(tag).parse(&b"ABCDEFGHIJKL"[..]);
+        assert_eq!(res, Ok((&b"KL"[..], tag)));
+        let res: IResult<_, _, Error<&[u8]>> = complete::tag_unrolled(tag).parse(&b"ABCDEFGHIJKL"[..]);
+        assert_eq!(res, Ok((&b"KL"[..], tag)));
 
-                i += 16;
-                left -= 16;
+        let res: IResult<_, _, Error<&[u8]>> = streaming::tag_unrolled(tag).parse(&b"ABC"[..]);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(7))));
+        let res: IResult<_, _, Error<&[u8]>> = complete::tag_unrolled(tag).parse(&b"ABC"[..]);
+        assert_eq!(res, Err(Err::Error(Error::new(&b"ABC"[..], ErrorKind::Tag))));
 
-                if left < 16 {
-                    break;
-                }
-            }
+        let res: IResult<_, _, Error<&[u8]>> = streaming::tag_unrolled(tag).parse(&b"ABCdef"[..]);
+        assert_eq!(res, Err(Err::Error(Error::new(&b"ABCdef"[..], ErrorKind::Tag))));
+    }
+
+    #[test]
+    fn sse42_fallback_test() {
+        let range = crate::make_ranges!(is_a);
+        let tag = &b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaa"[..];
+        let inputs = [
+            &b""[..],
+            &b"aaab"[..],
+            &b"aaaaaaaaaaaaaaaaaaab"[..],
+            &b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab"[..],
+            &b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"[..],
+        ];
+
+        for input in inputs.iter() {
+            assert_eq!(
+                take_while_ranges_index(&range, input),
+                take_while_unrolled_index(&is_a, input)
+            );
+            assert_eq!(tag_sse2_index(&TagMatcher::new(tag), input), tag_unrolled_index(tag, input));
         }
+    }
 
-        let mut i = i - start;
-        if !found {
-            loop {
-                if !predicate(unsafe { *input.get_unchecked(i) }) {
-                    break;
-                }
-                i = i + 1;
-                if i == input.len() {
-                    break;
+    #[test]
+    fn tag_matcher_test() {
+        let data = &b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ"[..];
+        for tag_len in 0..=40 {
+            let tag = &data[..tag_len];
+            for input in [data, &b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYz"[..]].iter() {
+                // every length, so the end of the input falls in every position of a chunk
+                for len in 0..=input.len() {
+                    let input = &input[..len];
+                    let expected = || -> IResult<&[u8], &[u8], Error<&[u8]>> {
+                        nom::bytes::streaming::tag(tag)(input)
+                    };
+                    assert_eq!(streaming::tag_sse2(tag).parse(input), expected());
+                    let expected = || -> IResult<&[u8], &[u8], Error<&[u8]>> {
+                        nom::bytes::complete::tag(tag)(input)
+                    };
+                    assert_eq!(complete::tag_sse2(tag).parse(input), expected());
                 }
             }
         }
+    }
 
-        if i == 0 {
-            Err(Err::Error(Error::from_error_kind(
-                input,
-                ErrorKind::TakeWhile1,
-            )))
-        } else if i == input.len() {
-            Err(Err::Incomplete(Needed::Unknown))
-        } else {
-            let (prefix, suffix) = input.split_at(i);
-            Ok((suffix, prefix))
+    #[test]
+    fn avx2_test() {
+        const fn is_token(c: u8) -> bool {
+            c > 0x20 && c < 0x7F
+        }
+        let ranges = crate::make_ranges!(is_token);
+
+        let mut input = [b'a'; 100];
+        for len in 0..100 {
+            for stop in 0..len {
+                input[stop] = b' ';
+                assert_eq!(
+                    take_while_ranges_index_wide(&ranges, &input[..len]),
+                    stop
+                );
+                input[stop] = 0x80;
+                assert_eq!(
+                    take_while_ranges_index_wide(&ranges, &input[..len]),
+                    stop
+                );
+                input[stop] = b'a';
+            }
+            assert_eq!(
+                take_while_ranges_index_wide(&ranges, &input[..len]),
+                len
+            );
         }
-    }
-}
 
-pub fn tag_unrolled<'a, 'b: 'a, Error: ParseError<&'a [u8]>>(
-    tag: &'b[u8]
-) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], &'a [u8], Error>
-{
-    move |input: &'a [u8]| {
-        let mut i = 0usize;
-        let len = std::cmp::min(tag.len(), input.len());
-        let mut found = false;
+        let input = &b"/abcd/efgh/ijkl/mnop/qrst/uvwx/yz/abcd/efgh HTTP/1.1"[..];
+        let res: IResult<_, _, Error<&[u8]>> = streaming::take_while1_avx2(ranges).parse(input);
+        assert_eq!(res, Ok((&b" HTTP/1.1"[..], &input[..43])));
+        let res: IResult<_, _, Error<&[u8]>> = streaming::take_while1_avx2(ranges).parse(&input[..43]);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(1))));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while1_avx2(ranges).parse(&input[..43]);
+        assert_eq!(res, Ok((&b""[..], &input[..43])));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while1_avx2(ranges).parse(&input[43..]);
+        assert_eq!(res, Err(Err::Error(Error::new(&input[43..], ErrorKind::TakeWhile1))));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while0_avx2(ranges).parse(&input[43..]);
+        assert_eq!(res, Ok((&input[43..], &b""[..])));
+    }
 
-        loop {
-            if len - i < 8 {
-                break;
+    #[test]
+    fn shufti_test() {
+        // scattered bytes, and a class needing the second pair of tables
+        const fn is_scattered(c: u8) -> bool {
+            c == b'a' || c == b'%' || c == b'~' || c == 0x80 || c == 0xFE
+        }
+        const fn is_staircase(c: u8) -> bool {
+            (c & 0x0F) <= (c >> 4)
+        }
+        let scattered = crate::make_shufti!(is_scattered);
+        let staircase = crate::make_shufti!(is_staircase);
+
+        let mut input = [b'a'; 100];
+        for len in 0..100 {
+            for stop in 0..len {
+                input[stop] = b'b';
+                let expected = take_while_unrolled_index(&is_scattered, &input[..len]);
+                assert_eq!(expected, stop);
+                assert_eq!(shufti_index(&scattered, true, &input[..len]), expected);
+                let till = take_while_unrolled_index(&|c| !is_scattered(c), &input[stop..len]);
+                assert_eq!(shufti_index(&scattered, false, &input[stop..len]), till);
+                #[cfg(target_arch = "x86_64")]
+                if crate::cpu::has_ssse3() {
+                    assert_eq!(unsafe { shufti_index_ssse3::<false>(&scattered, true, &input[..len], len) }, expected);
+                }
+                input[stop] = b'a';
             }
+            assert_eq!(shufti_index(&scattered, true, &input[..len]), len);
+        }
 
-            if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
-                found = true;
-                break;
+        let input: Vec<u8> = (0..=255u8).cycle().take(600).collect();
+        for start in 0..256 {
+            let expected = take_while_unrolled_index(&is_staircase, &input[start..]);
+            assert_eq!(shufti_index(&staircase, true, &input[start..]), expected);
+            let till = take_while_unrolled_index(&|c| !is_staircase(c), &input[start..]);
+            assert_eq!(shufti_index(&staircase, false, &input[start..]), till);
+            #[cfg(target_arch = "x86_64")]
+            if crate::cpu::has_ssse3() {
+                assert_eq!(unsafe { shufti_index_ssse3::<false>(&staircase, true, &input[start..], input.len() - start) }, expected);
+                assert_eq!(unsafe { shufti_index_ssse3::<false>(&staircase, false, &input[start..], input.len() - start) }, till);
             }
-            i = i + 1;
+        }
 
-            if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
-                found = true;
-                break;
-            }
-            i = i + 1;
+        let input = &b"a%~a%~a%~a%~a%~a%~a%~a%~a%~a%~a%~a%~ %"[..];
+        let res: IResult<_, _, Error<&[u8]>> = streaming::take_while1_shufti(&scattered).parse(input);
+        assert_eq!(res, Ok((&b" %"[..], &input[..36])));
+        let res: IResult<_, _, Error<&[u8]>> = streaming::take_while1_shufti(&scattered).parse(&input[..36]);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(1))));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while1_shufti(&scattered).parse(&input[..36]);
+        assert_eq!(res, Ok((&b""[..], &input[..36])));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while1_shufti(&scattered).parse(&input[36..]);
+        assert_eq!(res, Err(Err::Error(Error::new(&input[36..], ErrorKind::TakeWhile1))));
+    }
 
-            if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
-                found = true;
-                break;
+    #[test]
+    fn take_while_m_n_test() {
+        const fn is_hex(c: u8) -> bool {
+            (c >= b'0' && c <= b'9') || (c >= b'a' && c <= b'f') || (c >= b'A' && c <= b'F')
+        }
+        let ranges = crate::make_ranges!(is_hex);
+        let set = crate::make_shufti!(is_hex);
+
+        let inputs = [
+            &b""[..],
+            &b"1"[..],
+            &b"12"[..],
+            &b"12z"[..],
+            &b"1z"[..],
+            &b"z"[..],
+            &b"1234"[..],
+            &b"12345"[..],
+            &b"1234z"[..],
+            &b"123456"[..],
+            &b"12345z"[..],
+            &b"0123456789abcdef0123456789abcdef0123456789"[..],
+        ];
+        let bounds = [(0, 0), (0, 4), (2, 4), (4, 4), (2, 40), (5, 3)];
+
+        for &(m, n) in bounds.iter() {
+            for &input in inputs.iter() {
+                let expected: IResult<_, _, Error<&[u8]>> =
+                    nom::bytes::streaming::take_while_m_n(m, n, is_hex)(input);
+                let res: IResult<_, _, Error<&[u8]>> =
+                    streaming::take_while_m_n_unrolled(m, n, is_hex).parse(input);
+                assert_eq!(res, expected, "m={} n={} input={:?}", m, n, input);
+                let res: IResult<_, _, Error<&[u8]>> =
+                    streaming::take_while_m_n_sse2(m, n, ranges).parse(input);
+                assert_eq!(res, expected, "m={} n={} input={:?}", m, n, input);
+                let res: IResult<_, _, Error<&[u8]>> =
+                    streaming::take_while_m_n_avx2(m, n, ranges).parse(input);
+                assert_eq!(res, expected, "m={} n={} input={:?}", m, n, input);
+                let res: IResult<_, _, Error<&[u8]>> =
+                    streaming::take_while_m_n_shufti(m, n, &set).parse(input);
+                assert_eq!(res, expected, "m={} n={} input={:?}", m, n, input);
+
+                let expected: IResult<_, _, Error<&[u8]>> =
+                    nom::bytes::complete::take_while_m_n(m, n, is_hex)(input);
+                let res: IResult<_, _, Error<&[u8]>> =
+                    complete::take_while_m_n_unrolled(m, n, is_hex).parse(input);
+                assert_eq!(res, expected, "m={} n={} input={:?}", m, n, input);
+                let res: IResult<_, _, Error<&[u8]>> =
+                    complete::take_while_m_n_sse2(m, n, ranges).parse(input);
+                assert_eq!(res, expected, "m={} n={} input={:?}", m, n, input);
+                let res: IResult<_, _, Error<&[u8]>> =
+                    complete::take_while_m_n_avx2(m, n, ranges).parse(input);
+                assert_eq!(res, expected, "m={} n={} input={:?}", m, n, input);
+                let res: IResult<_, _, Error<&[u8]>> =
+                    complete::take_while_m_n_shufti(m, n, &set).parse(input);
+                assert_eq!(res, expected, "m={} n={} input={:?}", m, n, input);
             }
-            i = i + 1;
+        }
+    }
 
-            if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
-                found = true;
-                break;
-            }
-            i = i + 1;
+    fn same_as_nom<'a>(
+        res: IResult<&'a [u8], &'a [u8], Error<&'a [u8]>>,
+        expected: IResult<&'a [u8], &'a [u8], Error<&'a [u8]>>,
+    ) {
+        assert_eq!(res, expected);
+    }
 
-            if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
-                found = true;
-                break;
+    #[test]
+    fn take_till_is_a_is_not_test() {
+        let inputs = [
+            &b""[..],
+            &b" "[..],
+            &b"abc"[..],
+            &b"abc def"[..],
+            &b"\r\nabc"[..],
+            &b"abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz"[..],
+            &b"abcdefghijklmnopqrstuvwxyzabcdefghijklmnopq\trstuvwxyz"[..],
+            &b"abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz:"[..],
+        ];
+        let small = &b" \t\r\n"[..];
+        let large = &b"abcdefghijklmnopqrstuvwxyz"[..];
+        const fn is_colon(c: u8) -> bool {
+            c == b':'
+        }
+        let colon = crate::make_shufti!(is_colon);
+
+        for &input in inputs.iter() {
+            same_as_nom(
+                streaming::take_till_unrolled(is_colon).parse(input),
+                nom::bytes::streaming::take_till(is_colon)(input),
+            );
+            same_as_nom(
+                complete::take_till_unrolled(is_colon).parse(input),
+                nom::bytes::complete::take_till(is_colon)(input),
+            );
+            same_as_nom(
+                streaming::take_till1_unrolled(is_colon).parse(input),
+                nom::bytes::streaming::take_till1(is_colon)(input),
+            );
+            same_as_nom(
+                complete::take_till1_unrolled(is_colon).parse(input),
+                nom::bytes::complete::take_till1(is_colon)(input),
+            );
+            same_as_nom(
+                streaming::take_till_shufti(&colon).parse(input),
+                nom::bytes::streaming::take_till(is_colon)(input),
+            );
+            same_as_nom(
+                complete::take_till1_shufti(&colon).parse(input),
+                nom::bytes::complete::take_till1(is_colon)(input),
+            );
+
+            for &chars in [small, large].iter() {
+                let expected = || nom::bytes::streaming::is_a(chars)(input);
+                same_as_nom(streaming::is_a_unrolled(chars).parse(input), expected());
+                same_as_nom(streaming::is_a_sse2(chars).parse(input), expected());
+                same_as_nom(streaming::is_a_avx2(chars).parse(input), expected());
+                let expected = || nom::bytes::complete::is_a(chars)(input);
+                same_as_nom(complete::is_a_unrolled(chars).parse(input), expected());
+                same_as_nom(complete::is_a_sse2(chars).parse(input), expected());
+                same_as_nom(complete::is_a_avx2(chars).parse(input), expected());
+
+                let expected = || nom::bytes::streaming::is_not(chars)(input);
+                same_as_nom(streaming::is_not_unrolled(chars).parse(input), expected());
+                same_as_nom(streaming::is_not_sse2(chars).parse(input), expected());
+                same_as_nom(streaming::is_not_avx2(chars).parse(input), expected());
+                let expected = || nom::bytes::complete::is_not(chars)(input);
+                same_as_nom(complete::is_not_unrolled(chars).parse(input), expected());
+                same_as_nom(complete::is_not_sse2(chars).parse(input), expected());
+                same_as_nom(complete::is_not_avx2(chars).parse(input), expected());
+
+                let in_chars = |c: u8| chars.contains(&c);
+                let expected = || nom::bytes::streaming::take_till(in_chars)(input);
+                same_as_nom(streaming::take_till_sse2(chars).parse(input), expected());
+                same_as_nom(streaming::take_till_avx2(chars).parse(input), expected());
+                let expected = || nom::bytes::complete::take_till(in_chars)(input);
+                same_as_nom(complete::take_till_sse2(chars).parse(input), expected());
+                same_as_nom(complete::take_till_avx2(chars).parse(input), expected());
+                let expected = || nom::bytes::streaming::take_till1(in_chars)(input);
+                same_as_nom(streaming::take_till1_sse2(chars).parse(input), expected());
+                same_as_nom(streaming::take_till1_avx2(chars).parse(input), expected());
+                let expected = || nom::bytes::complete::take_till1(in_chars)(input);
+                same_as_nom(complete::take_till1_sse2(chars).parse(input), expected());
+                same_as_nom(complete::take_till1_avx2(chars).parse(input), expected());
             }
-            i = i + 1;
+        }
 
-            if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
-                found = true;
-                break;
+        // compare the SIMD versions with the lookup table on every position
+        let set = ByteSet::new(small);
+        let mut outside = [b'a'; 70];
+        let mut inside = [b' '; 70];
+        for len in 0..70 {
+            for stop in 0..len {
+                outside[stop] = b'\n';
+                assert_eq!(byte_set_index_unrolled(&set, false, &outside[..len]), stop);
+                assert_eq!(byte_set_index_sse2(&set, false, &outside[..len]), stop);
+                assert_eq!(byte_set_index_avx2(&set, false, &outside[..len]), stop);
+                outside[stop] = b'a';
+
+                inside[stop] = b'a';
+                assert_eq!(byte_set_index_unrolled(&set, true, &inside[..len]), stop);
+                assert_eq!(byte_set_index_sse2(&set, true, &inside[..len]), stop);
+                assert_eq!(byte_set_index_avx2(&set, true, &inside[..len]), stop);
+                inside[stop] = b' ';
             }
-            i = i + 1;
+            assert_eq!(byte_set_index_sse2(&set, false, &outside[..len]), len);
+            assert_eq!(byte_set_index_avx2(&set, true, &inside[..len]), len);
+        }
+    }
 
-            if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
-                found = true;
-                break;
+    #[test]
+    fn take_until_test() {
+        let tags = [&b""[..], &b"\n"[..], &b"\r\n"[..], &b"--boundary"[..], &b"aaab"[..]];
+        let mut inputs: Vec<Vec<u8>> = vec![
+            b"".to_vec(),
+            b"\r".to_vec(),
+            b"abc\r\ndef".to_vec(),
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab".to_vec(),
+            b"--boundar--boundar--boundar--boundary".to_vec(),
+        ];
+        // puts each tag at every position of a 80 bytes input
+        for &tag in tags.iter() {
+            for position in 0..80 {
+                let mut input = vec![b'a'; 80];
+                input[position..].iter_mut().zip(tag.iter()).for_each(|(i, t)| *i = *t);
+                inputs.push(input);
             }
-            i = i + 1;
+        }
 
-            if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
-                found = true;
-                break;
+        for &tag in tags.iter() {
+            for input in inputs.iter() {
+                let input = &input[..];
+                same_as_nom(
+                    streaming::take_until_sse2(tag).parse(input),
+                    nom::bytes::streaming::take_until(tag)(input),
+                );
+                same_as_nom(
+                    streaming::take_until_avx2(tag).parse(input),
+                    nom::bytes::streaming::take_until(tag)(input),
+                );
+                same_as_nom(
+                    complete::take_until_sse2(tag).parse(input),
+                    nom::bytes::complete::take_until(tag)(input),
+                );
+                same_as_nom(
+                    complete::take_until_avx2(tag).parse(input),
+                    nom::bytes::complete::take_until(tag)(input),
+                );
+                for start in 0..=input.len() {
+                    let expected = find_substring_scalar(tag, input, start);
+                    assert_eq!(find_substring_sse2(tag, input, start), expected);
+                    assert_eq!(find_substring_avx2(tag, input, start), expected);
+                }
             }
-            i = i + 1;
         }
+    }
 
-        if !found {
-            loop {
-                if unsafe { *tag.get_unchecked(i) != *input.get_unchecked(i) } {
-                    break;
-                }
-                i = i + 1;
-                if i == len {
-                    break;
+    #[test]
+    fn take_until_resumable_test() {
+        let data = &b"abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz\r\nabc"[..];
+        let mut parser = streaming::take_until_resumable(&b"\r\n"[..]);
+
+        let (res, start) = parser.parse_from::<Error<_>>(&data[..30], 0);
+        assert_eq!(res, Err(Err::Incomplete(Needed::Unknown)));
+        assert_eq!(start, 29);
+        let (res, start) = parser.parse_from::<Error<_>>(&data[..53], start);
+        assert_eq!(res, Err(Err::Incomplete(Needed::Unknown)));
+        assert_eq!(start, 52);
+        let (res, start) = parser.parse_from::<Error<_>>(data, start);
+        assert_eq!(res, Ok((&b"\r\nabc"[..], &data[..52])));
+        assert_eq!(start, 0);
+        assert_eq!(parser.parse::<Error<_>>(data), Ok((&b"\r\nabc"[..], &data[..52])));
+
+        // the bytes before `start` are not looked at again
+        let (res, _) = parser.parse_from::<Error<_>>(data, 53);
+        assert_eq!(res, Err(Err::Incomplete(Needed::Unknown)));
+        let (res, start) = parser.parse_from::<Error<_>>(&data[..10], 100);
+        assert_eq!(res, Err(Err::Incomplete(Needed::Unknown)));
+        assert_eq!(start, 9);
+
+        // the offset does not depend on the buffer, only on its content
+        let mut buffer = data[..30].to_vec();
+        let (_, start) = parser.parse_from::<Error<_>>(&buffer, 0);
+        buffer.extend_from_slice(&data[30..]);
+        buffer.shrink_to_fit();
+        assert_eq!(parser.parse_from::<Error<_>>(&buffer, start), (Ok((&buffer[52..], &buffer[..52])), 0));
+
+        let parser = complete::take_until_resumable(&b"\r\n"[..]);
+        assert_eq!(
+            parser.parse_from::<Error<_>>(&data[..30], 0),
+            (Err(Err::Error(Error::new(&data[..30], ErrorKind::TakeUntil))), 0)
+        );
+    }
+
+    #[test]
+    fn take_until_resume_test() {
+        let data = &b"abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz\r\nabc"[..];
+        // the capacity is reserved so the buffer is not moved when it grows
+        let mut buffer = Vec::with_capacity(data.len());
+        let mut sse2 = streaming::take_until_sse2(&b"\r\n"[..]);
+        let mut avx2 = streaming::take_until_avx2(&b"\r\n"[..]);
+        let mut resumable = streaming::take_until_resumable(&b"\r\n"[..]);
+
+        buffer.extend_from_slice(&data[..30]);
+        assert_eq!(sse2.parse::<Error<_>>(&buffer), Err(Err::Incomplete(Needed::Unknown)));
+        assert_eq!(Parser::<_, _, Error<_>>::parse(&mut avx2, &buffer[..]), Err(Err::Incomplete(Needed::Unknown)));
+        assert_eq!(resumable.parse::<Error<_>>(&buffer), Err(Err::Incomplete(Needed::Unknown)));
+
+        // a tag written in the part already scanned is not seen by the
+        // next calls, which only look at the bytes from offset 29
+        buffer[10..12].copy_from_slice(b"\r\n");
+        buffer.extend_from_slice(&data[30..]);
+        assert_eq!(sse2.parse::<Error<_>>(&buffer), Ok((&buffer[52..], &buffer[..52])));
+        assert_eq!(Parser::<_, _, Error<_>>::parse(&mut avx2, &buffer[..]), Ok((&buffer[52..], &buffer[..52])));
+        assert_eq!(resumable.parse::<Error<_>>(&buffer), Ok((&buffer[52..], &buffer[..52])));
+
+        // once a search is over, the next one starts from the beginning
+        assert_eq!(sse2.parse::<Error<_>>(&buffer), Ok((&buffer[10..], &buffer[..10])));
+        assert_eq!(avx2.parse::<Error<_>>(&buffer), Ok((&buffer[10..], &buffer[..10])));
+
+        // as does a search in another buffer
+        let mut other = streaming::take_until_avx2(&b"\r\n"[..]);
+        assert_eq!(other.parse::<Error<_>>(&data[..30]), Err(Err::Incomplete(Needed::Unknown)));
+        assert_eq!(other.parse::<Error<_>>(&buffer), Ok((&buffer[10..], &buffer[..10])));
+    }
+
+    #[test]
+    fn tag_no_case_test() {
+        let tags = [
+            &b""[..],
+            &b"GET"[..],
+            &b"content-length"[..],
+            &b"Transfer-Encoding: Chunked\r\n\r\n"[..],
+            &b"sElEcT * FROM users WHERE id = 1 AND name = '@[`{'"[..],
+        ];
+
+        for &tag in tags.iter() {
+            let mut inputs: Vec<Vec<u8>> = vec![
+                tag.to_ascii_uppercase(),
+                tag.to_ascii_lowercase(),
+                [tag, &b"abc"[..]].concat(),
+            ];
+            // every byte value at every position of the tag
+            for position in 0..tag.len() {
+                for c in 0..=255u8 {
+                    let mut input = tag.to_ascii_uppercase();
+                    input[position] = c;
+                    inputs.push(input);
                 }
             }
-        }
 
-        if i == tag.len() {
-            let (prefix, suffix) = input.split_at(i);
-            Ok((suffix, prefix))
-        } else {
-            if input.len() > i {
-                Err(Err::Error(Error::from_error_kind(input, ErrorKind::Tag)))
-            } else {
-                Err(Err::Incomplete(Needed::new(tag.len() - i)))
+            for input in inputs.iter() {
+                for len in 0..=input.len() {
+                    let input = &input[..len];
+
+                    let expected = || -> IResult<&[u8], &[u8], Error<&[u8]>> {
+                        nom::bytes::streaming::tag_no_case(tag)(input)
+                    };
+                    assert_eq!(streaming::tag_no_case_unrolled(tag).parse(input), expected());
+                    assert_eq!(streaming::tag_no_case_sse2(tag).parse(input), expected());
+                    assert_eq!(streaming::tag_no_case_avx2(tag).parse(input), expected());
+
+                    let expected = || -> IResult<&[u8], &[u8], Error<&[u8]>> {
+                        nom::bytes::complete::tag_no_case(tag)(input)
+                    };
+                    assert_eq!(complete::tag_no_case_unrolled(tag).parse(input), expected());
+                    assert_eq!(complete::tag_no_case_sse2(tag).parse(input), expected());
+                    assert_eq!(complete::tag_no_case_avx2(tag).parse(input), expected());
+                }
             }
         }
+
+        let res: IResult<_, _, Error<&[u8]>> = streaming::tag_no_case_avx2(&b"GET"[..]).parse(&b"g"[..]);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(2))));
     }
-}
 
-#[inline(always)]
-#[cfg(feature = "sse2")]
-pub fn tag_sse2<'a, 'b: 'a, Error: ParseError<&'a [u8]>>(
-    tag: &'b[u8],
-) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], &'a [u8], Error>
-{
-    move |input: &'a [u8]| {
-        use std::arch::x86_64::{
-            _mm_cmpestri, _mm_loadu_si128, _SIDD_CMP_EQUAL_EACH, _SIDD_LEAST_SIGNIFICANT,
-            _SIDD_UBYTE_OPS, _SIDD_NEGATIVE_POLARITY,
+    #[test]
+    fn parser_struct_test() {
+        use crate::mode::Complete;
+        use nom::sequence::{preceded, terminated};
+
+        static GET: TagUnrolled<'static> = streaming::tag_unrolled(b"GET ");
+
+        struct RequestLine<F: Fn(u8) -> bool> {
+            path: TakeWhile1Unrolled<F, Complete>,
+            version: TagNoCaseSse2<'static, Complete>,
+        }
+
+        let request_line = RequestLine {
+            path: complete::take_while1_unrolled(|c| c != b' '),
+            version: complete::tag_no_case_sse2(b" HTTP/1.1"),
         };
 
-        let mut found = false;
+        let input = &b"GET /index.html http/1.1\r\n"[..];
+        let res: IResult<_, _, Error<&[u8]>> = preceded(
+            GET,
+            terminated(request_line.path, request_line.version),
+        )(input);
+        assert_eq!(res, Ok((&b"\r\n"[..], &b"/index.html"[..])));
+
+        type BoxedParser<'a> = Box<dyn Parser<&'a [u8], &'a [u8], Error<&'a [u8]>>>;
+        let mut parsers: Vec<BoxedParser> = vec![
+            Box::new(GET),
+            Box::new(complete::is_a_avx2(b"GET")),
+            Box::new(complete::take_until_sse2(b" ")),
+        ];
+        let outputs: Vec<_> = parsers.iter_mut().map(|p| p.parse(input)).collect();
+        assert_eq!(
+            outputs,
+            vec![
+                Ok((&input[4..], &input[..4])),
+                Ok((&input[3..], &input[..3])),
+                Ok((&input[3..], &input[..3])),
+            ]
+        );
+    }
 
-        let mut index = 0;
-        loop {
-            let current_tag = &tag[index..];
-            let current_tag_len = std::cmp::min(current_tag.len(), 16) as i32;
-            let current_slice = &input[index..];
-            let current_slice_len = std::cmp::min(current_slice.len(), 16) as i32;
-
-            let idx = unsafe {
-                _mm_cmpestri(
-                    _mm_loadu_si128(current_tag.as_ptr() as *const _),
-                    current_tag_len,
-                    _mm_loadu_si128(current_slice.as_ptr() as *const _),
-                    current_slice_len,
-                    _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_EQUAL_EACH | _SIDD_UBYTE_OPS | _SIDD_NEGATIVE_POLARITY,
-                    )
-            };
+    #[test]
+    fn padded_test() {
+        use crate::padded::{PaddedBuf, PaddedInput};
+
+        // compares the results, the remaining inputs being compared by length
+        fn check<'a>(
+            padded: IResult<PaddedInput<'a>, &'a [u8], Error<PaddedInput<'a>>>,
+            expected: IResult<&'a [u8], &'a [u8], Error<&'a [u8]>>,
+        ) {
+            let padded = padded
+                .map(|(rest, output)| (rest.len(), output))
+                .map_err(|e| e.map(|e| (e.input.len(), e.code)));
+            let expected = expected
+                .map(|(rest, output)| (rest.len(), output))
+                .map_err(|e| e.map(|e| (e.input.len(), e.code)));
+            assert_eq!(padded, expected);
+        }
 
-            index += idx as usize;
-            if idx < 16 {
-                found = true;
-                break;
-            }
+        const fn is_token(c: u8) -> bool {
+            c > 0x20 && c < 0x7F
+        }
+        const fn is_zero_or_a(c: u8) -> bool {
+            c == 0 || c == b'a'
+        }
+        let token = crate::make_ranges!(is_token);
+        // accepts the padding bytes
+        let zero_or_a = crate::make_ranges!(is_zero_or_a);
+        let shufti = crate::make_shufti!(is_zero_or_a);
+
+        let mut input = vec![b'a'; 100];
+        input.extend_from_slice(b"\0\0 /index.html HTTP/1.1\r\n");
+
+        for start in 0..input.len() {
+            for len in 0..=input.len() - start {
+                let input = &input[start..start + len];
+                let buf = PaddedBuf::from(input);
+
+                check(streaming::take_while0_sse2(zero_or_a).parse_padded(buf.input()), streaming::take_while0_sse2(zero_or_a).parse(input));
+                check(complete::take_while1_sse2(token).parse_padded(buf.input()), complete::take_while1_sse2(token).parse(input));
+                check(streaming::take_while1_avx2(zero_or_a).parse_padded(buf.input()), streaming::take_while1_avx2(zero_or_a).parse(input));
+                check(complete::take_while0_avx2(zero_or_a).parse_padded(buf.input()), complete::take_while0_avx2(zero_or_a).parse(input));
+                check(streaming::take_while0_shufti(&shufti).parse_padded(buf.input()), streaming::take_while0_shufti(&shufti).parse(input));
+                check(complete::take_while1_shufti(&shufti).parse_padded(buf.input()), complete::take_while1_shufti(&shufti).parse(input));
+                check(streaming::take_till_shufti(&shufti).parse_padded(buf.input()), streaming::take_till_shufti(&shufti).parse(input));
+                check(complete::take_till1_shufti(&shufti).parse_padded(buf.input()), complete::take_till1_shufti(&shufti).parse(input));
+
+                check(streaming::take_while_m_n_sse2(2, 40, zero_or_a).parse_padded(buf.input()), streaming::take_while_m_n_sse2(2, 40, zero_or_a).parse(input));
+                check(complete::take_while_m_n_avx2(2, 40, zero_or_a).parse_padded(buf.input()), complete::take_while_m_n_avx2(2, 40, zero_or_a).parse(input));
+                check(streaming::take_while_m_n_shufti(2, 40, &shufti).parse_padded(buf.input()), streaming::take_while_m_n_shufti(2, 40, &shufti).parse(input));
+                check(complete::take_while_m_n_shufti(2, 40, &shufti).parse_padded(buf.input()), complete::take_while_m_n_shufti(2, 40, &shufti).parse(input));
+
+                // the padding bytes are in the set of `is_a`, not in the one of `is_not`
+                for &chars in [&b"a\0"[..], &b" "[..]].iter() {
+                    check(streaming::is_a_sse2(chars).parse_padded(buf.input()), streaming::is_a_sse2(chars).parse(input));
+                    check(complete::is_a_avx2(chars).parse_padded(buf.input()), complete::is_a_avx2(chars).parse(input));
+                    check(complete::is_not_sse2(chars).parse_padded(buf.input()), complete::is_not_sse2(chars).parse(input));
+                    check(streaming::is_not_avx2(chars).parse_padded(buf.input()), streaming::is_not_avx2(chars).parse(input));
+                    check(streaming::take_till_sse2(chars).parse_padded(buf.input()), streaming::take_till_sse2(chars).parse(input));
+                    check(complete::take_till1_avx2(chars).parse_padded(buf.input()), complete::take_till1_avx2(chars).parse(input));
+                }
 
-            if current_tag.len() <= 16 || current_slice.len() < 16 {
-                break;
+                // `\0\0` also matches the padding
+                for &tag in [&b"\0\0"[..], &b"\r\n"[..], &b"a"[..]].iter() {
+                    check(streaming::take_until_sse2(tag).parse_padded(buf.input()), streaming::take_until_sse2(tag).parse(input));
+                    check(complete::take_until_sse2(tag).parse_padded(buf.input()), complete::take_until_sse2(tag).parse(input));
+                    check(streaming::take_until_avx2(tag).parse_padded(buf.input()), streaming::take_until_avx2(tag).parse(input));
+                    check(complete::take_until_avx2(tag).parse_padded(buf.input()), complete::take_until_avx2(tag).parse(input));
+
+                    let parser = streaming::take_until_resumable(tag);
+                    let (res, resume) = parser.parse_padded_from(buf.input(), 0);
+                    let (expected, expected_resume) = parser.parse_from(input, 0);
+                    check(res, expected);
+                    assert_eq!(resume, expected_resume);
+                }
             }
         }
 
-        if index == tag.len() {
-            Ok((&input[index..], &input[..index]))
-        } else {
-            if found {
-                Err(Err::Error(Error::from_error_kind(input, ErrorKind::Tag)))
-            } else {
-                Err(Err::Incomplete(Needed::new(tag.len() - index)))
+        let tag = &b"aaaaaaaaaaaaaaaaaaaaaaaa"[..];
+        for len in 0..=input.len() {
+            let buf = PaddedBuf::from(&input[..len]);
+            check(streaming::tag_sse2(tag).parse_padded(buf.input()), streaming::tag_sse2(tag).parse(&input[..len]));
+            check(complete::tag_sse2(tag).parse_padded(buf.input()), complete::tag_sse2(tag).parse(&input[..len]));
+        }
+
+        let tags = [&b"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"[..], &b"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\0"[..]];
+        for &tag in tags.iter() {
+            for len in 0..=input.len() {
+                let buf = PaddedBuf::from(&input[..len]);
+                check(streaming::tag_no_case_sse2(tag).parse_padded(buf.input()), streaming::tag_no_case_sse2(tag).parse(&input[..len]));
+                check(complete::tag_no_case_sse2(tag).parse_padded(buf.input()), complete::tag_no_case_sse2(tag).parse(&input[..len]));
+                check(streaming::tag_no_case_avx2(tag).parse_padded(buf.input()), streaming::tag_no_case_avx2(tag).parse(&input[..len]));
+                check(complete::tag_no_case_avx2(tag).parse_padded(buf.input()), complete::tag_no_case_avx2(tag).parse(&input[..len]));
             }
         }
+
+        // used as a nom parser
+        let buf = PaddedBuf::from(&b"GET /index.html"[..]);
+        let res: IResult<_, _, Error<PaddedInput>> = nom::sequence::pair(
+            complete::tag_sse2(b"GET "),
+            complete::take_while1_shufti(&crate::make_shufti!(is_token)),
+        )(buf.input());
+        let (rest, output) = res.unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(output, (&b"GET "[..], &b"/index.html"[..]));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn sse2_mode_test() {
+        let range = crate::make_ranges!(is_a);
+        let input = &b"aaaaaaaaaaaaaaaaaaaa"[..];
+
+        let res: IResult<_, _, Error<&[u8]>> = streaming::take_while1_sse2(range).parse(input);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(1))));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while1_sse2(range).parse(input);
+        assert_eq!(res, Ok((&b""[..], input)));
+        let res: IResult<_, _, Error<&[u8]>> = complete::take_while0_sse2(range).parse(input);
+        assert_eq!(res, Ok((&b""[..], input)));
+
+        let tag = &b"ABCDEFGHIJ"[..];
+        let res: IResult<_, _, Error<&[u8]>> = streaming::tag_sse2(tag).parse(&b"ABC"[..]);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(7))));
+        let res: IResult<_, _, Error<&[u8]>> = complete::tag_sse2(tag).parse(&b"ABC"[..]);
+        assert_eq!(res, Err(Err::Error(Error::new(&b"ABC"[..], ErrorKind::Tag))));
+        let res: IResult<_, _, Error<&[u8]>> = complete::tag_sse2(tag).parse(&b"ABCDEFGHIJKL"[..]);
+        assert_eq!(res, Ok((&b"KL"[..], tag)));
+    }
 
     #[test]
-    #[cfg(feature = "sse2")]
     fn simd_test() {
         use std::str::from_utf8;
-        fn is_token(c: u8) -> bool {
+        const fn is_token(c: u8) -> bool {
             c > 0x20 && c < 0x7F
         }
 
-        let range = b"\0 \x7F\x7F";
-        let input = b"/abcd/efgh/ijkl/pouet/ 1234579";
+        let range = crate::make_ranges!(is_token);
         let input = b"/abcd/efgh/ij kl/pouet/ 1234579";
-        let res: IResult<&[u8], &[u8]> = take_while1_sse2(is_token, range)(input);
+        let res: IResult<&[u8], &[u8]> = streaming::take_while1_sse2(range).parse(input);
 
         let (i, o) = res.unwrap();
         assert_eq!(from_utf8(i).unwrap(), " kl/pouet/ 1234579");
         assert_eq!(from_utf8(o).unwrap(), "/abcd/efgh/ij");
     }
 
+    #[cfg(target_arch = "x86_64")]
     #[test]
-    #[cfg(feature = "sse2")]
     fn tag_simd_test() {
         use std::arch::x86_64::{
-            _mm_cmpestri, _mm_cmpestrm, _mm_loadu_si128, _SIDD_CMP_EQUAL_EACH, _SIDD_LEAST_SIGNIFICANT,
-            _SIDD_UBYTE_OPS, _SIDD_NEGATIVE_POLARITY
-        };
-
-        let tag1 = "ABCDABCDABCDABCD";
-        let tag2 = "ABCDABCDABCD";
-        let tag3 = "ABCDABCDABCDABCDEFGH";
-
-        let slice1 = "ABCDABCDABCDABCD";
-        let slice2 = "ABcdabcdabcdabcd";
-        let slice3 = "ABCDabcd";
-        let slice4 = "ABCDABCDABCDABCDABCD";
-
-        let idx = unsafe {
-            println!("tag1-> {:?}", _mm_loadu_si128(tag1.as_ptr() as *const _));
-            _mm_cmpestri(
-                _mm_loadu_si128(tag1.as_ptr() as *const _),
-                tag1.len() as i32,
-                _mm_loadu_si128(slice1.as_ptr() as *const _),
-                slice1.len() as i32,
-                _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_EQUAL_EACH | _SIDD_UBYTE_OPS | _SIDD_NEGATIVE_POLARITY,
-            )
+            __m128i, _mm_cmpestri, _mm_load_si128, _SIDD_CMP_EQUAL_EACH, _SIDD_LEAST_SIGNIFICANT,
+            _SIDD_NEGATIVE_POLARITY, _SIDD_UBYTE_OPS,
         };
-        println!("comparing tag \"{}\" to input \"{}\" gave {:?}, should be {}",
-          tag1, slice1, idx, 16);
 
-        let idx = unsafe {
-            _mm_cmpestri(
-                _mm_loadu_si128(tag1.as_ptr() as *const _),
-                tag1.len() as i32,
-                _mm_loadu_si128(slice2.as_ptr() as *const _),
-                slice2.len() as i32,
-                _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_EQUAL_EACH | _SIDD_UBYTE_OPS | _SIDD_NEGATIVE_POLARITY,
-            )
-        };
-        println!("comparing tag \"{}\" to input \"{}\" gave {:?}, should be {}",
-          tag1, slice2, idx, 2);
+        // index of the first difference between the first 16 bytes of `tag`
+        // and `input`, both loaded from aligned and zero padded copies
+        #[target_feature(enable = "sse4.2")]
+        unsafe fn first_mismatch(tag: &[u8], input: &[u8]) -> i32 {
+            let tag = TagMatcher::new(tag);
+            let mut data = Aligned16([0u8; 16]);
+            let len = std::cmp::min(input.len(), 16);
+            data.0[..len].copy_from_slice(&input[..len]);
 
-        let idx = unsafe {
             _mm_cmpestri(
-                _mm_loadu_si128(tag2.as_ptr() as *const _),
-                tag2.len() as i32,
-                _mm_loadu_si128(slice1.as_ptr() as *const _),
-                slice1.len() as i32,
+                _mm_load_si128(tag.chunks()[0].0.as_ptr() as *const __m128i),
+                std::cmp::min(tag.len(), 16) as i32,
+                _mm_load_si128(data.0.as_ptr() as *const __m128i),
+                len as i32,
                 _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_EQUAL_EACH | _SIDD_UBYTE_OPS | _SIDD_NEGATIVE_POLARITY,
             )
-        };
-        println!("comparing tag \"{}\" to input \"{}\" gave {:?}, should be {}",
-          tag2, slice1, idx, 12);
+        }
 
-        let idx = unsafe {
-            _mm_cmpestri(
-                _mm_loadu_si128(tag3.as_ptr() as *const _),
-                tag3.len() as i32,
-                _mm_loadu_si128(slice3.as_ptr() as *const _),
-                slice3.len() as i32,
-                _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_EQUAL_EACH | _SIDD_UBYTE_OPS | _SIDD_NEGATIVE_POLARITY,
-            )
-        };
-        println!("comparing tag \"{}\" to input \"{}\" gave {:?}, should be {}",
-          tag3, slice3, idx, 4);
+        if !crate::cpu::has_sse42() {
+            return;
+        }
 
-        let idx = unsafe {
-            _mm_cmpestri(
-                _mm_loadu_si128(tag3.as_ptr() as *const _),
-                tag3.len() as i32,
-                _mm_loadu_si128(slice4.as_ptr() as *const _),
-                slice4.len() as i32,
-                _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_EQUAL_EACH | _SIDD_UBYTE_OPS | _SIDD_NEGATIVE_POLARITY,
-            )
-        };
-        println!("comparing tag \"{}\" to input \"{}\" gave {:?}, should be {}",
-          tag3, slice4, idx, 16);
+        let tag1 = b"ABCDABCDABCDABCD";
+        let tag2 = b"ABCDABCDABCD";
+        let tag3 = b"ABCDABCDABCDABCDEFGH";
+
+        let slice1 = b"ABCDABCDABCDABCD";
+        let slice2 = b"ABcdabcdabcdabcd";
+        let slice3 = b"ABCDabcd";
+        let slice4 = b"ABCDABCDABCDABCDABCD";
+
+        unsafe {
+            assert_eq!(first_mismatch(tag1, slice1), 16);
+            assert_eq!(first_mismatch(tag1, slice2), 2);
+            // past the end of the tag, the input bytes do not match
+            assert_eq!(first_mismatch(tag2, slice1), 12);
+            assert_eq!(first_mismatch(tag3, slice3), 4);
+            assert_eq!(first_mismatch(tag3, slice4), 16);
+        }
     }
 }
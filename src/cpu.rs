@@ -0,0 +1,86 @@
+//! Runtime CPU feature detection
+//!
+//! The SIMD code paths are compiled with `#[target_feature]`, so they can
+//! be part of a binary running on CPUs that do not support them. The
+//! combinators check these flags before calling them, and fall back to a
+//! scalar implementation otherwise.
+//!
+//! Detection runs once, the result is then cached in an atomic.
+/* Copyright (C) 2020 Geoffroy Couprie */
+use std::sync::atomic::{AtomicU8, Ordering};
+
+const SSE42: u8 = 1;
+const AVX2: u8 = 1 << 1;
+const SSSE3: u8 = 1 << 2;
+const INITIALIZED: u8 = 1 << 7;
+
+static FEATURES: AtomicU8 = AtomicU8::new(0);
+
+#[inline(always)]
+fn features() -> u8 {
+    let features = FEATURES.load(Ordering::Relaxed);
+    if features & INITIALIZED != 0 {
+        features
+    } else {
+        detect()
+    }
+}
+
+#[cold]
+fn detect() -> u8 {
+    let mut features = INITIALIZED;
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("ssse3") {
+            features |= SSSE3;
+        }
+        if is_x86_feature_detected!("sse4.2") {
+            features |= SSE42;
+        }
+        if is_x86_feature_detected!("avx2") {
+            features |= AVX2;
+        }
+    }
+
+    FEATURES.store(features, Ordering::Relaxed);
+    features
+}
+
+/// `_mm_shuffle_epi8` is available
+#[inline(always)]
+pub fn has_ssse3() -> bool {
+    features() & SSSE3 != 0
+}
+
+/// `_mm_cmpestri` and the other SSE4.2 string instructions are available
+#[inline(always)]
+pub fn has_sse42() -> bool {
+    features() & SSE42 != 0
+}
+
+/// 256 bits integer vectors are available
+#[inline(always)]
+pub fn has_avx2() -> bool {
+    features() & AVX2 != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detection_is_cached() {
+        let first = features();
+        assert_ne!(first & INITIALIZED, 0);
+        assert_eq!(FEATURES.load(Ordering::Relaxed), first);
+        assert_eq!(features(), first);
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            assert_eq!(has_ssse3(), is_x86_feature_detected!("ssse3"));
+            assert_eq!(has_sse42(), is_x86_feature_detected!("sse4.2"));
+            assert_eq!(has_avx2(), is_x86_feature_detected!("avx2"));
+        }
+    }
+}
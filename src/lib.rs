@@ -1,4 +1,11 @@
 /* Copyright (C) 2020 Geoffroy Couprie */
+#[macro_use]
+pub mod mode;
+
+mod ascii;
 pub mod combinators;
+pub mod cpu;
 pub mod lut;
+pub mod matcher;
 pub mod avx;
+pub mod padded;
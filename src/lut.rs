@@ -4,7 +4,18 @@
 //! by replacing the matching function with a lookup table. However,
 //! writing those tables by hand is cumbersome, so we can instead use
 //! the  [make_lookup_table] macro to generate it at compile time.
+//!
+//! The same tables can describe the byte ranges used by the SSE4.2
+//! `take_while*_sse2` combinators, with [make_ranges] or
+//! [Ranges::from_lookup_table], or written as pairs of bounds with
+//! [Ranges::new]. Those combinators only take the [Ranges], which also keep
+//! the accepted bytes for their scalar code, so there is no separate
+//! predicate that could disagree with them.
+//!
+//! Byte classes that do not fit in 8 ranges can be described with
+//! [make_shufti] or [Shufti::from_lookup_table] instead.
 /* Copyright (C) 2020 Geoffroy Couprie */
+use crate::matcher::Aligned16;
 
 /*
  * this code oes not work yet because of this error: error[E0658]: function pointers cannot appear in constant functions. see https://github.com/rust-lang/rust/issues/57563 the feature can be activated by #![feature(const_fn_fn_ptr_basics)]
@@ -36,10 +47,275 @@ macro_rules! make_lookup_table (
   })
 );
 
+/// generates the [Ranges] describing the bytes rejected by a `const fn`
+/// predicate returning `bool` or `u8` (non zero meaning accepted)
+///
+/// The ranges are computed in a `const` item, so compilation fails if
+/// those bytes do not fit in 8 ranges, and the predicate cannot be a closure.
+#[macro_export]
+macro_rules! make_ranges (
+  ($f: expr) => ({
+    const RANGES: $crate::lut::Ranges = {
+      let mut array = [0u8; 256];
+
+      let mut i = 0u16;
+      while i <= 255 {
+          array[i as usize] = $f(i as u8) as u8;
+          i += 1;
+      }
+
+      $crate::lut::Ranges::from_lookup_table(&array, 0xFF)
+    };
+
+    RANGES
+  })
+);
+
+/// byte ranges in the format expected by `_mm_cmpestri` with `_SIDD_CMP_RANGES`
+///
+/// The `take_while*_sse2` combinators stop at the first byte that falls in
+/// one of the ranges. The accepted bytes are also kept as a 256 bits set, so
+/// the scalar code paths use exactly the same byte class.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ranges {
+    // always 16 zero padded bytes, so the aligned SIMD load does not read
+    // past the end
+    bytes: Aligned16,
+    len: usize,
+    // bit `c & 63` of `accepted[c >> 6]` is set if `c` is not in the ranges
+    accepted: [u64; 4],
+}
+
+impl Ranges {
+    /// the ranges of bytes `c` for which `table[c] & mask == 0`
+    ///
+    /// Panics (or fails compilation in a const context) if there are
+    /// more than 8 ranges.
+    pub const fn from_lookup_table(table: &[u8; 256], mask: u8) -> Ranges {
+        let mut bytes = [0u8; 16];
+        let mut len = 0usize;
+        let mut in_range = false;
+        let mut accepted = [0u64; 4];
+
+        let mut i = 0usize;
+        while i < 256 {
+            let rejected = table[i] & mask == 0;
+            if !rejected {
+                accepted[i >> 6] |= 1 << (i & 63);
+            }
+
+            if rejected && !in_range {
+                if len == 16 {
+                    panic!("the byte class cannot be expressed in 8 ranges");
+                }
+                bytes[len] = i as u8;
+                in_range = true;
+            } else if !rejected && in_range {
+                bytes[len + 1] = (i - 1) as u8;
+                len += 2;
+                in_range = false;
+            }
+
+            i += 1;
+        }
+
+        if in_range {
+            bytes[len + 1] = 0xFF;
+            len += 2;
+        }
+
+        Ranges { bytes: Aligned16(bytes), len, accepted }
+    }
+
+    /// ranges given as `low, high` pairs of bytes, like `b"\0 \x7F\xFF"`
+    ///
+    /// Panics (or fails compilation in a const context) if there are more
+    /// than 8 ranges, if a range is missing its upper bound, or if its
+    /// bounds are reversed.
+    pub const fn new(ranges: &[u8]) -> Ranges {
+        if ranges.len() > 16 {
+            panic!("at most 8 ranges are supported");
+        }
+        if ranges.len() & 1 == 1 {
+            panic!("the ranges must be pairs of bytes");
+        }
+
+        let mut bytes = [0u8; 16];
+        let mut accepted = [u64::MAX; 4];
+
+        let mut i = 0usize;
+        while i < ranges.len() {
+            let (low, high) = (ranges[i], ranges[i + 1]);
+            if low > high {
+                panic!("the lower bound of a range is above its upper bound");
+            }
+            bytes[i] = low;
+            bytes[i + 1] = high;
+
+            let mut c = low as usize;
+            while c <= high as usize {
+                accepted[c >> 6] &= !(1 << (c & 63));
+                c += 1;
+            }
+
+            i += 2;
+        }
+
+        Ranges { bytes: Aligned16(bytes), len: ranges.len(), accepted }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes.0[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// the ranges followed by zeroes
+    pub(crate) fn padded(&self) -> &Aligned16 {
+        &self.bytes
+    }
+
+    /// `true` if `c` is in one of the ranges
+    #[inline(always)]
+    pub fn contains(&self, c: u8) -> bool {
+        !self.accepts(c)
+    }
+
+    /// `true` if `c` is accepted by the predicate the ranges were built
+    /// from, so it is not in any of the ranges
+    #[inline(always)]
+    pub fn accepts(&self, c: u8) -> bool {
+        self.accepted[(c >> 6) as usize] & (1 << (c & 63)) != 0
+    }
+}
+
+impl AsRef<[u8]> for Ranges {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// generates the [Shufti] tables for the bytes accepted by a `const fn`
+/// predicate returning `bool` or `u8` (non zero meaning accepted)
+#[macro_export]
+macro_rules! make_shufti (
+  ($f: expr) => ({
+    let mut array = [0u8; 256];
+
+    let mut i = 0u16;
+    while i <= 255 {
+        array[i as usize] = $f(i as u8) as u8;
+        i += 1;
+    }
+
+    $crate::lut::Shufti::from_lookup_table(&array, 0xFF)
+  })
+);
+
+/// nibble tables for the "shufti" byte classification
+///
+/// A byte `c` is accepted if `lo[c & 0xF] & hi[c >> 4] != 0`, which a SIMD
+/// byte shuffle computes for a whole vector at once. Each bit of the
+/// tables stands for a set of accepted low nibbles: high nibbles that
+/// accept the same low nibbles share a bit. A set needing more than 8
+/// of those is split over a second pair of tables, so any set of bytes
+/// can be represented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shufti {
+    lo: [[u8; 16]; 2],
+    hi: [[u8; 16]; 2],
+}
+
+impl Shufti {
+    /// the set of bytes `c` for which `table[c] & mask != 0`
+    pub const fn from_lookup_table(table: &[u8; 256], mask: u8) -> Shufti {
+        // accepted low nibbles, for each high nibble
+        let mut patterns = [0u16; 16];
+        let mut h = 0usize;
+        while h < 16 {
+            let mut l = 0usize;
+            while l < 16 {
+                if table[(h << 4) | l] & mask != 0 {
+                    patterns[h] |= 1 << l;
+                }
+                l += 1;
+            }
+            h += 1;
+        }
+
+        let mut lo = [[0u8; 16]; 2];
+        let mut hi = [[0u8; 16]; 2];
+        let mut distinct = [0u16; 16];
+        let mut count = 0usize;
+
+        let mut h = 0usize;
+        while h < 16 {
+            if patterns[h] != 0 {
+                let mut bit = 0usize;
+                while bit < count && distinct[bit] != patterns[h] {
+                    bit += 1;
+                }
+                if bit == count {
+                    distinct[count] = patterns[h];
+                    count += 1;
+                }
+
+                hi[bit / 8][h] |= 1 << (bit % 8);
+            }
+            h += 1;
+        }
+
+        let mut bit = 0usize;
+        while bit < count {
+            let mut l = 0usize;
+            while l < 16 {
+                if distinct[bit] & (1 << l) != 0 {
+                    lo[bit / 8][l] |= 1 << (bit % 8);
+                }
+                l += 1;
+            }
+            bit += 1;
+        }
+
+        Shufti { lo, hi }
+    }
+
+    /// `true` if `c` is in the set
+    #[inline(always)]
+    pub fn contains(&self, c: u8) -> bool {
+        let l = (c & 0x0F) as usize;
+        let h = (c >> 4) as usize;
+        (self.lo[0][l] & self.hi[0][h]) | (self.lo[1][l] & self.hi[1][h]) != 0
+    }
+
+    /// the low nibble tables
+    pub fn lo(&self) -> &[[u8; 16]; 2] {
+        &self.lo
+    }
+
+    /// the high nibble tables
+    pub fn hi(&self) -> &[[u8; 16]; 2] {
+        &self.hi
+    }
+
+    /// `true` if the second pair of tables is needed
+    pub fn is_split(&self) -> bool {
+        self.hi[1] != [0u8; 16]
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     const fn is_header_value_token(c: u8) -> u8 {
-        if c == '\t' as u8 || (c > 31 && c != 127) {
+        if c == b'\t' || (c > 31 && c != 127) {
             1
         } else {
             0
@@ -51,4 +327,99 @@ mod tests {
     fn print() {
         println!("LOOKUP_TABLE:\n{:#?}", LOOKUP_TABLE);
     }
+
+    const fn is_token(c: u8) -> bool {
+        c > 0x20 && c < 0x7F
+    }
+
+    const fn is_alphanumeric(c: u8) -> bool {
+        (c >= b'0' && c <= b'9') || (c >= b'A' && c <= b'Z') || (c >= b'a' && c <= b'z')
+    }
+
+    const TOKEN_RANGES: Ranges = make_ranges!(is_token);
+    const HEADER_VALUE_RANGES: Ranges = Ranges::from_lookup_table(&LOOKUP_TABLE, 1);
+    const ALPHANUMERIC_RANGES: Ranges = make_ranges!(is_alphanumeric);
+
+    #[test]
+    fn ranges() {
+        assert_eq!(TOKEN_RANGES.as_bytes(), &b"\0 \x7F\xFF"[..]);
+        assert_eq!(HEADER_VALUE_RANGES.as_bytes(), &b"\0\x08\x0A\x1F\x7F\x7F"[..]);
+        assert_eq!(ALPHANUMERIC_RANGES.as_bytes(), &b"\0/:@[`{\xFF"[..]);
+
+        for c in 0..=255u8 {
+            assert_eq!(TOKEN_RANGES.accepts(c), is_token(c));
+            assert_eq!(ALPHANUMERIC_RANGES.accepts(c), is_alphanumeric(c));
+            let in_ranges = ALPHANUMERIC_RANGES
+                .as_bytes()
+                .chunks(2)
+                .any(|range| range[0] <= c && c <= range[1]);
+            assert_eq!(ALPHANUMERIC_RANGES.contains(c), in_ranges);
+        }
+
+        let all = Ranges::from_lookup_table(&[1u8; 256], 1);
+        assert_eq!(all.as_bytes(), &b""[..]);
+        let none = Ranges::from_lookup_table(&[0u8; 256], 1);
+        assert_eq!(none.as_bytes(), &b"\0\xFF"[..]);
+        assert_eq!(none.padded().0, *b"\0\xFF\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+        assert_eq!(none.padded() as *const Aligned16 as usize % 16, 0);
+        // the ranges and the bit set, not a whole lookup table
+        assert!(std::mem::size_of::<Ranges>() <= 64);
+
+        assert_eq!(Ranges::new(b"\0 \x7F\xFF"), TOKEN_RANGES);
+        assert_eq!(Ranges::new(b"\0/:@[`{\xFF"), ALPHANUMERIC_RANGES);
+        assert_eq!(Ranges::new(b""), all);
+    }
+
+    #[test]
+    #[should_panic(expected = "lower bound of a range is above")]
+    fn ranges_reversed() {
+        Ranges::new(b"za");
+    }
+
+    // RFC 7230 tchar
+    const fn is_tchar(c: u8) -> bool {
+        is_alphanumeric(c)
+            || c == b'!' || c == b'#' || c == b'$' || c == b'%' || c == b'&' || c == b'\''
+            || c == b'*' || c == b'+' || c == b'-' || c == b'.' || c == b'^' || c == b'_'
+            || c == b'`' || c == b'|' || c == b'~'
+    }
+
+    const TCHAR: Shufti = make_shufti!(is_tchar);
+
+    #[test]
+    fn shufti() {
+        for c in 0..=255u8 {
+            assert_eq!(TCHAR.contains(c), is_tchar(c));
+        }
+        assert!(!TCHAR.is_split());
+
+        // every high nibble accepts a different set of low nibbles
+        let mut table = [0u8; 256];
+        for (c, entry) in table.iter_mut().enumerate() {
+            let (h, l) = (c >> 4, c & 0xF);
+            *entry = (l <= h) as u8;
+        }
+        let set = Shufti::from_lookup_table(&table, 1);
+        assert!(set.is_split());
+        for c in 0..=255u8 {
+            assert_eq!(set.contains(c), table[c as usize] != 0);
+        }
+
+        let header_value = Shufti::from_lookup_table(&LOOKUP_TABLE, 1);
+        for c in 0..=255u8 {
+            assert_eq!(header_value.contains(c), is_header_value_token(c) != 0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be expressed in 8 ranges")]
+    fn too_many_ranges() {
+        let mut table = [0u8; 256];
+        let mut i = 0;
+        while i < 256 {
+            table[i] = 1;
+            i += 16;
+        }
+        Ranges::from_lookup_table(&table, 1);
+    }
 }
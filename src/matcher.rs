@@ -0,0 +1,90 @@
+//! Precompiled matchers
+//!
+//! The SSE4.2 string instructions always load 16 bytes. Instead of loading
+//! them from the caller's tag on every call, which reads past the end of
+//! short slices, the combinators copy it once, at construction, into owned,
+//! zero padded and 16 bytes aligned buffers. The byte ranges are stored the
+//! same way by [Ranges](crate::lut::Ranges), which is also exported here as
+//! [RangeSet].
+/* Copyright (C) 2020 Geoffroy Couprie */
+
+/// 16 bytes that can be loaded with `_mm_load_si128`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(align(16))]
+pub(crate) struct Aligned16(pub(crate) [u8; 16]);
+
+/// the precompiled byte ranges of the `take_while*_sse2` and
+/// `take_while*_avx2` combinators
+///
+/// Build it from `low, high` pairs with `RangeSet::new`, or from a predicate
+/// with `make_ranges!`.
+pub type RangeSet = crate::lut::Ranges;
+
+/// a tag split in 16 bytes chunks, the last one being zero padded
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagMatcher {
+    chunks: Vec<Aligned16>,
+    len: usize,
+}
+
+impl TagMatcher {
+    pub fn new(tag: &[u8]) -> Self {
+        let chunks = tag
+            .chunks(16)
+            .map(|chunk| {
+                let mut padded = Aligned16([0u8; 16]);
+                padded.0[..chunk.len()].copy_from_slice(chunk);
+                padded
+            })
+            .collect();
+
+        TagMatcher {
+            chunks,
+            len: tag.len(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(crate) fn chunks(&self) -> &[Aligned16] {
+        &self.chunks
+    }
+
+    /// the tag, without the padding
+    pub fn as_bytes(&self) -> &[u8] {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(self.chunks.as_ptr() as *const u8, self.chunks.len() * 16)
+        };
+        &bytes[..self.len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_matcher() {
+        for len in 0..50 {
+            let tag: Vec<u8> = (1..=len as u8).collect();
+            let matcher = TagMatcher::new(&tag);
+
+            assert_eq!(matcher.len(), len);
+            assert_eq!(matcher.as_bytes(), &tag[..]);
+            assert_eq!(matcher.chunks().len(), len.div_ceil(16));
+            for chunk in matcher.chunks() {
+                assert_eq!(chunk as *const Aligned16 as usize % 16, 0);
+            }
+            // the padding is made of zeroes
+            if let Some(last) = matcher.chunks().last() {
+                assert!(last.0[(len - 1) % 16 + 1..].iter().all(|&b| b == 0));
+            }
+        }
+    }
+}
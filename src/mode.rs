@@ -0,0 +1,74 @@
+//! Input modes
+//!
+//! nom's byte combinators come in two flavours: the `streaming` ones return
+//! `Err::Incomplete` when they reach the end of their input, while the
+//! `complete` ones consider that the input they got is all there is.
+//!
+//! The specialized combinators take the mode as a type parameter, so both
+//! versions share the same fast path, and the check on the mode is resolved
+//! at compile time.
+/* Copyright (C) 2020 Geoffroy Couprie */
+
+pub trait Mode {
+    /// `true` if reaching the end of the input means more data is needed
+    const STREAMING: bool;
+}
+
+/// nom's `bytes::streaming` behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Streaming;
+
+impl Mode for Streaming {
+    const STREAMING: bool = true;
+}
+
+/// nom's `bytes::complete` behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Complete;
+
+impl Mode for Complete {
+    const STREAMING: bool = false;
+}
+
+/// declares the `streaming` and `complete` modules, with a wrapper fixing the
+/// mode of each listed `_mode` constructor
+///
+/// the entries are written as `fn name[generics](args) -> Type[args] = name_mode;`,
+/// the mode is appended to the type parameters of the returned type
+macro_rules! mode_modules {
+    (@wrappers $mode:ident;) => {};
+    (@wrappers $mode:ident; const fn $($rest:tt)*) => {
+        mode_modules!(@wrapper $mode [const] $($rest)*);
+    };
+    (@wrappers $mode:ident; fn $($rest:tt)*) => {
+        mode_modules!(@wrapper $mode [] $($rest)*);
+    };
+    (@wrapper $mode:ident [$($qualifier:tt)*] $name:ident [$($generics:tt)*]
+        ($($arg:ident: $arg_ty:ty),*) -> $ty:ident [$($ty_arg:tt),*]
+        = $generic:ident; $($rest:tt)*) => {
+        // the mode of the generic constructor is inferred from the return type
+        #[inline(always)]
+        pub $($qualifier)* fn $name<$($generics)*>($($arg: $arg_ty),*) -> $ty<$($ty_arg,)* $mode> {
+            super::$generic($($arg),*)
+        }
+
+        mode_modules!(@wrappers $mode; $($rest)*);
+    };
+    ($($entries:tt)*) => {
+        /// combinators returning `Err::Incomplete` when they reach the end of the input
+        pub mod streaming {
+            use super::*;
+            use crate::mode::Streaming;
+
+            mode_modules!(@wrappers Streaming; $($entries)*);
+        }
+
+        /// combinators considering the end of the input as the end of the data
+        pub mod complete {
+            use super::*;
+            use crate::mode::Complete;
+
+            mode_modules!(@wrappers Complete; $($entries)*);
+        }
+    };
+}
@@ -0,0 +1,289 @@
+//! Inputs with SIMD read slack
+//!
+//! The SIMD combinators load 16 or 32 bytes at a time, so on a plain slice
+//! they have to finish with a scalar loop, or copy the end of the input to
+//! a temporary buffer. A [PaddedBuf] keeps [PADDING] zeroed bytes after its
+//! content, so the `parse_padded` methods of the parsers can load full
+//! vectors up to the end of the input and mask the bytes past it instead.
+//!
+//! A `parse_padded` method returns the same result as the `parse` method of
+//! the same parser on `input.as_bytes()`, with the remaining input as a
+//! [PaddedInput], and the parsers having one also implement `nom::Parser`
+//! over [PaddedInput].
+/* Copyright (C) 2020 Geoffroy Couprie */
+
+/// number of readable bytes after the end of a [PaddedInput]
+pub const PADDING: usize = 64;
+
+/// growable buffer followed by [PADDING] zeroed bytes
+///
+/// consumed bytes are only moved out once they take more room than the
+/// rest of the content, so consuming the input piece by piece stays linear
+#[derive(Clone, Debug)]
+pub struct PaddedBuf {
+    // `start` consumed bytes, the content, then `PADDING` zeroes
+    data: Vec<u8>,
+    start: usize,
+}
+
+impl PaddedBuf {
+    pub fn new() -> Self {
+        PaddedBuf {
+            data: vec![0u8; PADDING],
+            start: 0,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut data = Vec::with_capacity(capacity + PADDING);
+        data.resize(PADDING, 0);
+        PaddedBuf { data, start: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len() - PADDING - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[self.start..self.start + self.len()]
+    }
+
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        let end = self.data.len() - PADDING;
+        self.data.truncate(end);
+        self.data.extend_from_slice(bytes);
+        self.data.resize(end + bytes.len() + PADDING, 0);
+    }
+
+    /// removes the first `count` bytes, usually the ones a parser consumed
+    pub fn consume(&mut self, count: usize) {
+        assert!(count <= self.len(), "cannot consume more than the buffer length");
+        self.start += count;
+        if self.start >= self.len() {
+            self.data.drain(..self.start);
+            self.start = 0;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.data.resize(PADDING, 0);
+        self.start = 0;
+    }
+
+    pub fn input(&self) -> PaddedInput<'_> {
+        PaddedInput {
+            data: &self.data[self.start..],
+            len: self.len(),
+        }
+    }
+}
+
+impl PartialEq for PaddedBuf {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for PaddedBuf {}
+
+impl Default for PaddedBuf {
+    fn default() -> Self {
+        PaddedBuf::new()
+    }
+}
+
+impl From<&[u8]> for PaddedBuf {
+    fn from(bytes: &[u8]) -> Self {
+        let mut buf = PaddedBuf::with_capacity(bytes.len());
+        buf.extend_from_slice(bytes);
+        buf
+    }
+}
+
+/// view on the content of a [PaddedBuf], or a part of it
+///
+/// at least [PADDING] bytes can be read after its end: the zeroes of the
+/// buffer, or the rest of its content for a prefix returned by
+/// `InputTake::take`, so the combinators mask them with the length instead
+/// of relying on their value. It implements
+/// nom's `InputLength`, `InputTake` and `InputIter`, and gets
+/// `InputTakeAtPosition` from them, so it can also be used with nom's
+/// `take`, `take_while` or `take_till` combinators
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaddedInput<'a> {
+    // readable bytes, masked by `len`: the input, then at least `PADDING`
+    // more bytes
+    data: &'a [u8],
+    len: usize,
+}
+
+impl<'a> PaddedInput<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        &self.data[..self.len]
+    }
+
+    /// the input followed by the padding
+    pub(crate) fn with_padding(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// returns the input after the first `count` bytes, and those bytes
+    ///
+    /// panics if `count` is larger than the input
+    pub fn take_split(&self, count: usize) -> (PaddedInput<'a>, &'a [u8]) {
+        assert!(count <= self.len, "cannot take more than the input length");
+        let (prefix, suffix) = self.data.split_at(count);
+        let rest = PaddedInput {
+            data: suffix,
+            len: self.len - count,
+        };
+        (rest, &prefix[..count])
+    }
+}
+
+impl<'a> nom::InputLength for PaddedInput<'a> {
+    fn input_len(&self) -> usize {
+        self.len
+    }
+}
+
+// the bytes after a prefix are the rest of the input, then the padding
+impl<'a> nom::InputTake for PaddedInput<'a> {
+    fn take(&self, count: usize) -> Self {
+        assert!(count <= self.len, "cannot take more than the input length");
+        PaddedInput {
+            data: self.data,
+            len: count,
+        }
+    }
+
+    fn take_split(&self, count: usize) -> (Self, Self) {
+        let (rest, _) = PaddedInput::take_split(self, count);
+        (rest, nom::InputTake::take(self, count))
+    }
+}
+
+impl<'a> nom::InputIter for PaddedInput<'a> {
+    type Item = u8;
+    type Iter = std::iter::Enumerate<Self::IterElem>;
+    type IterElem = std::iter::Copied<std::slice::Iter<'a, u8>>;
+
+    fn iter_indices(&self) -> Self::Iter {
+        self.iter_elements().enumerate()
+    }
+
+    fn iter_elements(&self) -> Self::IterElem {
+        self.as_bytes().iter().copied()
+    }
+
+    fn position<P>(&self, predicate: P) -> Option<usize>
+    where
+        P: Fn(Self::Item) -> bool,
+    {
+        self.as_bytes().iter().position(|&c| predicate(c))
+    }
+
+    fn slice_index(&self, count: usize) -> Result<usize, nom::Needed> {
+        self.as_bytes().slice_index(count)
+    }
+}
+
+impl<'a> nom::UnspecializedInput for PaddedInput<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padded_buf() {
+        let mut buf = PaddedBuf::from(&b"GET /"[..]);
+        buf.extend_from_slice(b" HTTP/1.1\r\n");
+        assert_eq!(buf.as_bytes(), b"GET / HTTP/1.1\r\n");
+        assert_eq!(buf.input().with_padding().len(), buf.len() + PADDING);
+        assert!(buf.input().with_padding()[buf.len()..].iter().all(|&b| b == 0));
+
+        let (rest, method) = buf.input().take_split(4);
+        assert_eq!(method, b"GET ");
+        assert_eq!(rest.as_bytes(), b"/ HTTP/1.1\r\n");
+        assert_eq!(rest.with_padding().len(), rest.len() + PADDING);
+
+        buf.consume(6);
+        assert_eq!(buf.as_bytes(), b"HTTP/1.1\r\n");
+        assert!(buf.input().with_padding()[buf.len()..].iter().all(|&b| b == 0));
+
+        buf.clear();
+        assert!(buf.is_empty());
+        assert_eq!(buf.input().with_padding(), &[0u8; PADDING][..]);
+    }
+
+    #[test]
+    fn consume_and_refill() {
+        let mut buf = PaddedBuf::new();
+        let mut expected = Vec::new();
+
+        for round in 0..20u8 {
+            let chunk: Vec<u8> = (0..37u8).map(|i| i.wrapping_mul(7) ^ round).collect();
+            buf.extend_from_slice(&chunk);
+            expected.extend_from_slice(&chunk);
+
+            for _ in 0..3 {
+                buf.consume(5);
+                expected.drain(..5);
+                assert_eq!(buf.as_bytes(), &expected[..]);
+                assert_eq!(buf.input().as_bytes(), &expected[..]);
+                assert_eq!(buf.input().with_padding().len(), buf.len() + PADDING);
+                assert!(buf.input().with_padding()[buf.len()..].iter().all(|&b| b == 0));
+                // the consumed bytes never outgrow the content
+                assert!(buf.start <= buf.len());
+            }
+        }
+
+        assert_eq!(buf, PaddedBuf::from(&expected[..]));
+
+        buf.consume(buf.len());
+        assert!(buf.is_empty());
+        assert_eq!(buf.input().with_padding(), &[0u8; PADDING][..]);
+
+        buf.extend_from_slice(b"GET /");
+        assert_eq!(buf.as_bytes(), b"GET /");
+        assert!(buf.input().with_padding()[buf.len()..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot take more than the input length")]
+    fn take_split_past_end() {
+        let buf = PaddedBuf::from(&b"GET"[..]);
+        buf.input().take_split(4);
+    }
+
+    #[test]
+    fn nom_combinators() {
+        use nom::error::Error;
+        use nom::IResult;
+
+        let buf = PaddedBuf::from(&b"GET / HTTP/1.1"[..]);
+        let input = buf.input();
+
+        let res: IResult<_, _, Error<_>> = nom::bytes::complete::take_till(|c| c == b' ')(input);
+        let (rest, method) = res.unwrap();
+        assert_eq!(method.as_bytes(), b"GET");
+        assert_eq!(method.with_padding().len(), buf.len() + PADDING);
+        assert_eq!(rest.as_bytes(), b" / HTTP/1.1");
+
+        let res: IResult<_, _, Error<_>> = nom::bytes::streaming::take(20usize)(rest);
+        assert_eq!(res, Err(nom::Err::Incomplete(nom::Needed::new(9))));
+    }
+}